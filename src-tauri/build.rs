@@ -1,3 +1,51 @@
+// White-label settings, baked in at compile time. Each comes from an
+// environment variable, then from branding.toml (or the file named by
+// CHATTO_BRANDING_FILE), then from the defaults below.
+const BRANDING: &[(&str, &str, &str)] = &[
+    // (env var, branding.toml key, default)
+    ("CHATTO_DEFAULT_SERVER_URL", "default_server_url", "https://chat.chatto.run"),
+    ("CHATTO_PRODUCT_NAME", "product_name", "Chatto"),
+    ("CHATTO_WEBSITE_URL", "website_url", "https://github.com/teal-bauer/chatto-tauri"),
+    ("CHATTO_WEBSITE_LABEL", "website_label", "GitHub"),
+];
+
+fn branding() {
+    println!("cargo:rerun-if-env-changed=CHATTO_BRANDING_FILE");
+    let path = std::env::var("CHATTO_BRANDING_FILE").unwrap_or_else(|_| "branding.toml".into());
+    // Cargo treats a missing watched file as changed and would rerun this
+    // script on every build. A branding.toml added later is picked up with
+    // the next change to CHATTO_BRANDING_FILE or a clean build.
+    if std::path::Path::new(&path).exists() {
+        println!("cargo:rerun-if-changed={path}");
+    }
+
+    let file = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents
+            .parse::<toml::Table>()
+            .unwrap_or_else(|e| panic!("{path}: {e}")),
+        Err(_) => toml::Table::new(),
+    };
+
+    for (var, key, default) in BRANDING {
+        println!("cargo:rerun-if-env-changed={var}");
+        let value = match std::env::var(var) {
+            Ok(value) => value,
+            Err(_) => match file.get(*key) {
+                Some(toml::Value::String(value)) => value.clone(),
+                Some(_) => panic!("{path}: {key} must be a string"),
+                None => default.to_string(),
+            },
+        };
+        if value.trim().is_empty() {
+            panic!("{var} / {key} must not be empty");
+        }
+        if key.ends_with("_url") && !(value.starts_with("https://") || value.starts_with("http://")) {
+            panic!("{var} / {key} must be an http:// or https:// URL, got \"{value}\"");
+        }
+        println!("cargo:rustc-env={var}={value}");
+    }
+}
+
 fn main() {
     // Pass git describe output as GIT_VERSION env var at compile time.
     // Omit --dirty in CI since the build process may touch tracked files.
@@ -16,5 +64,7 @@ fn main() {
         .unwrap_or_else(|| env!("CARGO_PKG_VERSION").to_string());
     println!("cargo:rustc-env=GIT_VERSION={}", version.trim());
 
+    branding();
+
     tauri_build::build()
 }
@@ -0,0 +1,147 @@
+// Backups of config.json and recovery from a damaged one.
+//
+// tauri-plugin-store writes the file in place, so power loss during a save
+// can leave it truncated, and the store then loads empty: the user is back
+// on the default server with no explanation. After every successful save the
+// file is copied to backups/config-<UTC timestamp>.json next to it, keeping
+// the newest few. At startup, before anything opens the store, a config.json
+// that doesn't parse is moved aside and the newest valid backup restored.
+
+use std::path::{Path, PathBuf};
+
+use tauri::Manager;
+
+const KEEP: usize = 5;
+
+fn config_path(app: &tauri::AppHandle) -> Option<PathBuf> {
+    Some(app.path().app_data_dir().ok()?.join(crate::config_file()))
+}
+
+fn backup_dir(config: &Path) -> PathBuf {
+    config.with_file_name("backups")
+}
+
+/// A store file is usable when it holds a JSON object.
+fn is_valid(contents: &[u8]) -> bool {
+    matches!(
+        serde_json::from_slice::<serde_json::Value>(contents),
+        Ok(serde_json::Value::Object(_))
+    )
+}
+
+/// Backups in `dir`, newest first. The timestamped names sort by age.
+fn list(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("config-") && n.ends_with(".json"))
+        })
+        .collect();
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+/// "20261017-093012", UTC.
+fn timestamp() -> String {
+    chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+/// Copy the just-saved config.json into the backup rotation. Skipped when it
+/// matches the newest backup, so repeated saves of the same settings don't
+/// push older, different ones out.
+pub fn rotate(app: &tauri::AppHandle) {
+    let Some(config) = config_path(app) else {
+        return;
+    };
+    let Ok(contents) = std::fs::read(&config) else {
+        return;
+    };
+    if !is_valid(&contents) {
+        return;
+    }
+
+    let dir = backup_dir(&config);
+    let existing = list(&dir);
+    if existing
+        .first()
+        .and_then(|p| std::fs::read(p).ok())
+        .is_some_and(|newest| newest == contents)
+    {
+        return;
+    }
+
+    let path = dir.join(format!("config-{}.json", timestamp()));
+    if let Err(e) = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, &contents)) {
+        eprintln!("failed to back up settings: {e}");
+        return;
+    }
+    for old in list(&dir).into_iter().skip(KEEP) {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+/// Check config.json before the store loads it. A damaged file is renamed to
+/// config.json.corrupt-<timestamp> and replaced with the newest backup that
+/// parses; the user is told either way.
+pub fn recover(app: &tauri::AppHandle) {
+    let Some(config) = config_path(app) else {
+        return;
+    };
+    let Ok(contents) = std::fs::read(&config) else {
+        // Missing (first run) or unreadable; nothing to recover from
+        return;
+    };
+    if is_valid(&contents) {
+        return;
+    }
+
+    let corrupt = config.with_file_name(format!("config.json.corrupt-{}", timestamp()));
+    if let Err(e) = std::fs::rename(&config, &corrupt) {
+        eprintln!("failed to move damaged settings aside: {e}");
+        return;
+    }
+    eprintln!("config.json is damaged, kept as {}", corrupt.display());
+
+    let restored = list(&backup_dir(&config)).into_iter().find(|backup| {
+        std::fs::read(backup)
+            .map(|b| is_valid(&b) && std::fs::write(&config, b).is_ok())
+            .unwrap_or(false)
+    });
+
+    let body = match &restored {
+        Some(backup) => {
+            // config-20261017-093012 → 2026-10-17 09:30
+            let taken = backup
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.strip_prefix("config-"))
+                .filter(|t| t.len() == 15 && t.is_ascii())
+                .map(|t| format!("{}-{}-{} {}:{}", &t[0..4], &t[4..6], &t[6..8], &t[9..11], &t[11..13]) + " UTC")
+                .unwrap_or_else(|| "an earlier session".into());
+            eprintln!("restored settings from {}", backup.display());
+            format!(
+                "Your settings file was damaged, so {} restored the backup from {taken}. Changes made since then may be missing.",
+                crate::PRODUCT_NAME
+            )
+        }
+        None => format!(
+            "Your settings file was damaged and no backup was available, so {} started with default settings.",
+            crate::PRODUCT_NAME
+        ),
+    };
+
+    use tauri_plugin_notification::NotificationExt;
+    let _ = app
+        .notification()
+        .builder()
+        .title(&format!("{} settings restored", crate::PRODUCT_NAME))
+        .body(body)
+        .show();
+}
@@ -0,0 +1,226 @@
+// Unread indicator outside the webview: a count drawn onto the tray icon,
+// the macOS dock badge, a Windows taskbar overlay and the Unity LauncherEntry
+// D-Bus signal that GNOME/KDE docks read on Linux.
+//
+// Counts come from the notification subscription (one per notification raised
+// while the window is in the background, cleared on focus) unless the web app
+// reports exact numbers through the set_unread_count command.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use tauri::Manager;
+
+static UNREAD: AtomicU32 = AtomicU32::new(0);
+static MENTIONS: AtomicU32 = AtomicU32::new(0);
+
+// Counts waiting for the LauncherEntry thread, which owns the session bus
+// connection.
+#[cfg(target_os = "linux")]
+static LAUNCHER: std::sync::OnceLock<std::sync::mpsc::Sender<u32>> = std::sync::OnceLock::new();
+
+// 3×5 bitmap digits for the tray overlay, one byte per row, low 3 bits used.
+#[cfg(any(target_os = "windows", all(not(target_os = "macos"), feature = "tray")))]
+const GLYPHS: [[u8; 5]; 11] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111], // 0
+    [0b010, 0b110, 0b010, 0b010, 0b111], // 1
+    [0b111, 0b001, 0b111, 0b100, 0b111], // 2
+    [0b111, 0b001, 0b111, 0b001, 0b111], // 3
+    [0b101, 0b101, 0b111, 0b001, 0b001], // 4
+    [0b111, 0b100, 0b111, 0b001, 0b111], // 5
+    [0b111, 0b100, 0b111, 0b101, 0b111], // 6
+    [0b111, 0b001, 0b001, 0b001, 0b001], // 7
+    [0b111, 0b101, 0b111, 0b101, 0b111], // 8
+    [0b111, 0b101, 0b111, 0b001, 0b111], // 9
+    [0b000, 0b010, 0b111, 0b010, 0b000], // +
+];
+
+pub fn set(app: &tauri::AppHandle, unread: u32, mentions: u32) {
+    UNREAD.store(unread, Ordering::SeqCst);
+    MENTIONS.store(mentions, Ordering::SeqCst);
+    apply(app);
+}
+
+/// Count one more notification that arrived in the background.
+pub fn increment(app: &tauri::AppHandle) {
+    UNREAD.fetch_add(1, Ordering::SeqCst);
+    apply(app);
+}
+
+pub fn clear(app: &tauri::AppHandle) {
+    let unread = UNREAD.swap(0, Ordering::SeqCst);
+    let mentions = MENTIONS.swap(0, Ordering::SeqCst);
+    if unread == 0 && mentions == 0 {
+        return;
+    }
+    apply(app);
+}
+
+fn apply(app: &tauri::AppHandle) {
+    let unread = UNREAD.load(Ordering::SeqCst);
+    let mentions = MENTIONS.load(Ordering::SeqCst);
+    // Only the tray tooltip shows mentions
+    #[cfg(not(feature = "tray"))]
+    let _ = mentions;
+
+    #[cfg(feature = "tray")]
+    if let Some(tray) = app.tray_by_id("main") {
+        let tooltip = match (unread, mentions) {
+            (0, _) => crate::PRODUCT_NAME.to_string(),
+            (n, 0) => format!("{} — {n} unread", crate::PRODUCT_NAME),
+            (n, m) => format!("{} — {n} unread, {m} mentions", crate::PRODUCT_NAME),
+        };
+        let _ = tray.set_tooltip(Some(tooltip));
+
+        // The menu bar icon is a template image that macOS recolours, so a
+        // drawn badge would lose its colour; show the count as text instead.
+        #[cfg(target_os = "macos")]
+        let _ = tray.set_title(Some(unread).filter(|n| *n > 0).map(|n| n.to_string()));
+        #[cfg(not(target_os = "macos"))]
+        if let Ok(icon) = tray_icon(unread) {
+            let _ = tray.set_icon(Some(icon));
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_badge_count(Some(unread as i64).filter(|n| *n > 0));
+    }
+    #[cfg(target_os = "windows")]
+    if let Some(window) = app.get_webview_window("main") {
+        let icon = if unread > 0 { overlay_icon(unread).ok() } else { None };
+        let _ = window.set_overlay_icon(icon);
+    }
+
+    #[cfg(target_os = "linux")]
+    publish_launcher_entry(app, unread);
+}
+
+/// The tray icon with `count` drawn in a red circle over its top-right corner.
+#[cfg(all(not(target_os = "macos"), feature = "tray"))]
+fn tray_icon(count: u32) -> tauri::Result<tauri::image::Image<'static>> {
+    let base = tauri::image::Image::from_bytes(crate::TRAY_ICON_BYTES)?;
+    let (width, height) = (base.width(), base.height());
+    let mut rgba = base.rgba().to_vec();
+    if count > 0 {
+        draw_badge(&mut rgba, width, height, count, width * 3 / 5);
+    }
+    Ok(tauri::image::Image::new_owned(rgba, width, height))
+}
+
+/// A standalone badge for the Windows taskbar overlay slot.
+#[cfg(target_os = "windows")]
+fn overlay_icon(count: u32) -> tauri::Result<tauri::image::Image<'static>> {
+    const SIZE: u32 = 32;
+    let mut rgba = vec![0; (SIZE * SIZE * 4) as usize];
+    draw_badge(&mut rgba, SIZE, SIZE, count, SIZE);
+    Ok(tauri::image::Image::new_owned(rgba, SIZE, SIZE))
+}
+
+#[cfg(any(target_os = "windows", all(not(target_os = "macos"), feature = "tray")))]
+fn draw_badge(rgba: &mut [u8], width: u32, height: u32, count: u32, diameter: u32) {
+    let mut put = |x: u32, y: u32, color: [u8; 4]| {
+        if x < width && y < height {
+            let i = ((y * width + x) * 4) as usize;
+            rgba[i..i + 4].copy_from_slice(&color);
+        }
+    };
+
+    // Red disc anchored to the top-right corner
+    let r = diameter as f32 / 2.0;
+    let (cx, cy) = (width as f32 - r, r);
+    for y in 0..diameter {
+        for x in (width - diameter)..width {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            if dx * dx + dy * dy <= r * r {
+                put(x, y, [0xe5, 0x3e, 0x3e, 0xff]);
+            }
+        }
+    }
+
+    // White digits, "9+" past nine so the text stays legible at tray size
+    let glyphs: Vec<usize> = if count > 9 {
+        vec![9, 10]
+    } else {
+        vec![count as usize]
+    };
+    let chars = glyphs.len() as u32;
+    let scale = (diameter / (4 * chars + 3)).max(1);
+    let text_width = (chars * 4 - 1) * scale;
+    let text_height = 5 * scale;
+    let left = (cx - text_width as f32 / 2.0).round().max(0.0) as u32;
+    let top = (cy - text_height as f32 / 2.0).round().max(0.0) as u32;
+
+    for (i, glyph) in glyphs.iter().enumerate() {
+        let gx = left + i as u32 * 4 * scale;
+        for (row, bits) in GLYPHS[*glyph].iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                for sy in 0..scale {
+                    for sx in 0..scale {
+                        put(gx + col * scale + sx, top + row as u32 * scale + sy, [0xff; 4]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Emit com.canonical.Unity.LauncherEntry.Update for our .desktop entry.
+/// Dash-to-Dock, Plasma's task manager and others listen for it on the
+/// session bus regardless of sender.
+///
+/// apply runs on the main thread (clear is called on focus), so the signal
+/// goes out from a background thread that connects to the bus once and only
+/// sends the latest count when several are queued.
+#[cfg(target_os = "linux")]
+fn publish_launcher_entry(app: &tauri::AppHandle, count: u32) {
+    let sender = LAUNCHER.get_or_init(|| {
+        let (sender, receiver) = std::sync::mpsc::channel::<u32>();
+        let app_uri = format!("application://{}.desktop", app.package_info().name);
+        std::thread::spawn(move || {
+            let mut connection = None;
+            while let Ok(mut count) = receiver.recv() {
+                while let Ok(newer) = receiver.try_recv() {
+                    count = newer;
+                }
+                if connection.is_none() {
+                    connection = zbus::blocking::Connection::session()
+                        .map_err(|e| eprintln!("failed to connect to the session bus: {e}"))
+                        .ok();
+                }
+                let Some(connection) = &connection else {
+                    continue;
+                };
+                if let Err(e) = emit_launcher_entry(connection, &app_uri, count) {
+                    eprintln!("failed to publish launcher badge: {e}");
+                }
+            }
+        });
+        sender
+    });
+    let _ = sender.send(count);
+}
+
+#[cfg(target_os = "linux")]
+fn emit_launcher_entry(
+    connection: &zbus::blocking::Connection,
+    app_uri: &str,
+    count: u32,
+) -> zbus::Result<()> {
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    let mut properties: HashMap<&str, Value> = HashMap::new();
+    properties.insert("count", Value::from(count as i64));
+    properties.insert("count-visible", Value::from(count > 0));
+    connection.emit_signal(
+        None::<&str>,
+        "/run/chatto/desktop/launcher",
+        "com.canonical.Unity.LauncherEntry",
+        "Update",
+        &(app_uri, properties),
+    )
+}
@@ -0,0 +1,195 @@
+// Command-line flags for the desktop binary, for onboarding scripts and
+// autostart entries that need to configure the app without the settings page.
+//
+// Positional arguments are ignored: on Linux and Windows the OS passes
+// chatto:// links that way, and the deep-link plugin picks them up itself.
+
+use std::sync::OnceLock;
+
+const USAGE: &str = "\
+Usage: chatto [OPTIONS]
+
+Options:
+  --server-url <URL>   Open this server for this launch only (not saved)
+  --hidden             Start in the tray without showing the window
+  --profile <NAME>     Use a separate config and data directory
+  --reset-settings     Clear stored settings before starting
+  --autostart          Launched by the login item (honours \"start minimized\")
+  --version            Print the version and exit
+  --help               Print this help and exit";
+
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    pub server_url: Option<String>,
+    pub hidden: bool,
+    pub profile: Option<String>,
+    pub reset_settings: bool,
+    pub autostart: bool,
+}
+
+pub enum Command {
+    Run(Args),
+    PrintVersion,
+    PrintHelp,
+}
+
+static ARGS: OnceLock<Args> = OnceLock::new();
+
+/// Flags this process was started with.
+pub fn args() -> &'static Args {
+    ARGS.get_or_init(Args::default)
+}
+
+/// Parse `argv` (without the program name).
+pub fn parse(argv: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = Args::default();
+    let mut argv = argv.into_iter();
+
+    while let Some(arg) = argv.next() {
+        // Accept both "--flag value" and "--flag=value"
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| argv.next())
+                .ok_or_else(|| format!("{name} needs a value"))
+        };
+
+        match flag.as_str() {
+            "--server-url" => {
+                let url = value("--server-url")?;
+                let parsed: tauri::Url = url
+                    .parse()
+                    .map_err(|e| format!("--server-url: invalid URL: {e}"))?;
+                if !matches!(parsed.scheme(), "http" | "https") {
+                    return Err("--server-url: must be an http:// or https:// URL".into());
+                }
+                args.server_url = Some(url);
+            }
+            "--hidden" => args.hidden = true,
+            "--profile" => {
+                let name = value("--profile")?;
+                let valid = !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                if !valid {
+                    return Err("--profile: use letters, digits, '-' and '_' only".into());
+                }
+                args.profile = Some(name);
+            }
+            "--reset-settings" => args.reset_settings = true,
+            "--autostart" => args.autostart = true,
+            "--version" | "-V" => return Ok(Command::PrintVersion),
+            "--help" | "-h" => return Ok(Command::PrintHelp),
+            other if other.starts_with('-') => {
+                eprintln!("ignoring unknown option {other}");
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Command::Run(args))
+}
+
+/// Parse the process arguments. Handles --version/--help and bad flags by
+/// printing and exiting; otherwise records the flags for args().
+pub fn init() {
+    match parse(std::env::args().skip(1)) {
+        Ok(Command::Run(args)) => {
+            let _ = ARGS.set(args);
+        }
+        Ok(Command::PrintVersion) => {
+            println!("{} {}", crate::PRODUCT_NAME, env!("GIT_VERSION"));
+            std::process::exit(0);
+        }
+        Ok(Command::PrintHelp) => {
+            println!("{USAGE}");
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(argv: &[&str]) -> Result<Command, String> {
+        parse(argv.iter().map(|arg| arg.to_string()))
+    }
+
+    fn run(argv: &[&str]) -> Args {
+        match parse_args(argv) {
+            Ok(Command::Run(args)) => args,
+            Ok(_) => panic!("{argv:?} did not parse as a launch"),
+            Err(e) => panic!("{argv:?}: {e}"),
+        }
+    }
+
+    #[test]
+    fn server_url_needs_a_value() {
+        let error = parse_args(&["--server-url"]).err().unwrap();
+        assert_eq!(error, "--server-url needs a value");
+
+        // The next flag isn't taken for a URL
+        assert!(parse_args(&["--server-url", "--hidden"]).is_err());
+        assert!(parse_args(&["--server-url="]).is_err());
+        assert!(parse_args(&["--server-url", "ftp://chat.example.org"]).is_err());
+    }
+
+    #[test]
+    fn server_url_both_forms() {
+        let spaced = run(&["--server-url", "https://chat.example.org"]);
+        let inline = run(&["--server-url=https://chat.example.org"]);
+        assert_eq!(
+            spaced.server_url.as_deref(),
+            Some("https://chat.example.org")
+        );
+        assert_eq!(inline.server_url, spaced.server_url);
+    }
+
+    #[test]
+    fn profile_both_forms() {
+        assert_eq!(run(&["--profile=work"]).profile.as_deref(), Some("work"));
+        assert_eq!(run(&["--profile", "work"]).profile.as_deref(), Some("work"));
+        assert!(parse_args(&["--profile"]).is_err());
+        assert!(parse_args(&["--profile="]).is_err());
+        assert!(parse_args(&["--profile", "../work"]).is_err());
+    }
+
+    #[test]
+    fn unknown_flags_and_positionals_are_ignored() {
+        let args = run(&[
+            "--frobnicate",
+            "chatto://chat.example.org/chat/s1/r1",
+            "--hidden",
+        ]);
+        assert!(args.hidden);
+        assert_eq!(args.server_url, None);
+    }
+
+    #[test]
+    fn hidden_with_autostart() {
+        let args = run(&["--autostart", "--hidden"]);
+        assert!(args.hidden);
+        assert!(args.autostart);
+        assert!(!args.reset_settings);
+        assert_eq!(args.profile, None);
+    }
+
+    #[test]
+    fn version_and_help_stop_parsing() {
+        assert!(matches!(
+            parse_args(&["--version", "--bogus="]),
+            Ok(Command::PrintVersion)
+        ));
+        assert!(matches!(parse_args(&["-h"]), Ok(Command::PrintHelp)));
+    }
+}
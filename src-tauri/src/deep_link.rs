@@ -0,0 +1,328 @@
+// chatto:// link routing.
+//
+// Links name an instance by host and carry a web app path:
+//
+//   chatto://chat.example.org/chat/<space>/<room>
+//   chatto://chat.example.org:8443/chat/DM/<room>
+//   chatto://chat.example.org            (just open that instance)
+//
+// chatto://add-instance?url=https://chat.example.org&name=Acme is an invite:
+// it opens the settings page on a confirmation prompt, and the instance is
+// only added (after the usual reachability check) once the user confirms.
+//
+// The host is matched against the configured instances and the path, query
+// and fragment are replayed on that instance's own URL, switching instances
+// first when needed. Links that don't parse or name an unknown host are
+// rejected with a notification instead of being handed to the webview.
+
+use tauri::Manager;
+
+use crate::instances::{self, Instance};
+
+/// Resolve a chatto:// link to the instance it targets and the https URL to
+/// load there. Prefers the active instance when several share the host.
+pub fn resolve(
+    link: &tauri::Url,
+    instances: &[Instance],
+    active_id: &str,
+) -> Result<(Instance, tauri::Url), String> {
+    if link.scheme() != "chatto" {
+        return Err(format!("Unsupported link scheme \"{}\"", link.scheme()));
+    }
+    let host = link
+        .host_str()
+        .filter(|h| !h.is_empty())
+        .ok_or_else(|| format!("The link does not name a {} server", crate::PRODUCT_NAME))?;
+
+    let matches = |instance: &&Instance| {
+        instance
+            .url
+            .parse::<tauri::Url>()
+            .map(|u| u.host_str() == Some(host) && u.port() == link.port())
+            .unwrap_or(false)
+    };
+    let instance = instances
+        .iter()
+        .filter(matches)
+        .find(|i| i.id == active_id)
+        .or_else(|| instances.iter().find(matches))
+        .cloned()
+        .ok_or_else(|| format!("{host} is not one of your {} instances. Add it in Settings first.", crate::PRODUCT_NAME))?;
+
+    let mut url: tauri::Url = instance
+        .url
+        .parse()
+        .map_err(|e| format!("Invalid instance URL: {e}"))?;
+    if link.path() != "/" && !link.path().is_empty() {
+        url.set_path(link.path());
+    }
+    url.set_query(link.query());
+    url.set_fragment(link.fragment());
+    Ok((instance, url))
+}
+
+/// Whether `link` is an add-instance invite rather than a link into one of
+/// the instances.
+#[cfg(desktop)]
+fn is_invite(link: &tauri::Url) -> bool {
+    link.scheme() == "chatto" && link.host_str() == Some("add-instance")
+}
+
+/// Settings page URL asking the user to confirm an add-instance invite.
+#[cfg(desktop)]
+pub fn invite_url(link: &tauri::Url) -> Result<tauri::Url, String> {
+    let mut url = None;
+    let mut name = None;
+    for (key, value) in link.query_pairs() {
+        match key.as_ref() {
+            "url" => url = Some(value.into_owned()),
+            "name" => name = Some(value.trim().chars().take(64).collect::<String>()),
+            _ => {}
+        }
+    }
+
+    let url = url.ok_or("The invite link has no server URL")?;
+    let parsed: tauri::Url = url
+        .parse()
+        .map_err(|_| format!("The invite link's server URL \"{url}\" is not valid"))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!("The invite link's server URL \"{url}\" is not valid"));
+    }
+    crate::policy::check_url(parsed.as_str())?;
+
+    let mut settings = crate::frontend_url("/");
+    {
+        let mut query = settings.query_pairs_mut();
+        query.append_pair("settings", "");
+        query.append_pair("add_url", parsed.as_str());
+        if let Some(name) = name.filter(|n| !n.is_empty()) {
+            query.append_pair("add_name", &name);
+        }
+    }
+    Ok(settings)
+}
+
+/// Route a chatto:// link received at launch or while running.
+pub fn handle(app: &tauri::AppHandle, link: &tauri::Url) {
+    #[cfg(desktop)]
+    if is_invite(link) {
+        let result = invite_url(link).and_then(|url| match app.get_webview_window("main") {
+            Some(window) => {
+                let _ = window.unminimize();
+                let _ = window.show();
+                let _ = window.set_focus();
+                window.navigate(url).map_err(|e| e.to_string())
+            }
+            None => {
+                crate::set_start_url(url);
+                Ok(())
+            }
+        });
+        if let Err(reason) = result {
+            reject(app, link, &reason);
+        }
+        return;
+    }
+
+    let active = instances::active(app);
+    let result = resolve(link, &instances::list(app), &active.id)
+        .and_then(|(instance, url)| open(app, &active, &instance, url));
+    if let Err(reason) = result {
+        reject(app, link, &reason);
+    }
+}
+
+/// Show `url`, a page on one of the instances, in the main window, switching
+/// to that instance first when another one is active.
+pub fn open_url(app: &tauri::AppHandle, url: tauri::Url) -> Result<(), String> {
+    let active = instances::active(app);
+    let on_instance = |instance: &Instance| {
+        instance
+            .url
+            .parse::<tauri::Url>()
+            .is_ok_and(|u| u.origin() == url.origin())
+    };
+    // The active instance may be a --server-url one that isn't in the list
+    if on_instance(&active) {
+        return open(app, &active, &active, url);
+    }
+    let instance = instances::list(app)
+        .into_iter()
+        .find(|i| on_instance(i))
+        .ok_or_else(|| {
+            let origin = url.origin().ascii_serialization();
+            format!("{origin} is no longer one of your instances")
+        })?;
+    open(app, &active, &instance, url)
+}
+
+fn reject(app: &tauri::AppHandle, link: &tauri::Url, reason: &str) {
+    eprintln!("rejected deep link {link}: {reason}");
+    use tauri_plugin_notification::NotificationExt;
+    let _ = app
+        .notification()
+        .builder()
+        .title(&format!("Can't open {} link", crate::PRODUCT_NAME))
+        .body(reason)
+        .show();
+}
+
+fn open(
+    app: &tauri::AppHandle,
+    active: &Instance,
+    instance: &Instance,
+    url: tauri::Url,
+) -> Result<(), String> {
+    let window = app.get_webview_window("main");
+
+    #[cfg(desktop)]
+    if let Some(window) = &window {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+
+    match window {
+        Some(window) if instance.id == active.id => window.navigate(url).map_err(|e| e.to_string()),
+        Some(_) => {
+            crate::set_start_url(url);
+            crate::switch_instance(app.clone(), instance.id.clone())
+        }
+        // Link received at launch: the main window is about to be created
+        // and picks up the start URL then.
+        None => {
+            crate::set_start_url(url);
+            instances::set_active(app, &instance.id).map(|_| ())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(id: &str, url: &str) -> Instance {
+        Instance {
+            id: id.into(),
+            label: instances::label_for(url),
+            url: url.into(),
+            default_data_store: false,
+        }
+    }
+
+    fn link(link: &str) -> tauri::Url {
+        link.parse().unwrap()
+    }
+
+    #[test]
+    fn resolve_prefers_active_instance_on_shared_host() {
+        let instances = [
+            instance("work", "https://chat.example.org"),
+            instance("club", "https://chat.example.org/club"),
+            instance("other", "https://other.example.org"),
+        ];
+        let to_room = link("chatto://chat.example.org/chat/s1/r1?thread=t1#latest");
+
+        let (target, url) = resolve(&to_room, &instances, "club").unwrap();
+        assert_eq!(target.id, "club");
+        assert_eq!(
+            url.as_str(),
+            "https://chat.example.org/chat/s1/r1?thread=t1#latest"
+        );
+
+        // Active instance is elsewhere: the first one on the host
+        let (target, _) = resolve(&to_room, &instances, "other").unwrap();
+        assert_eq!(target.id, "work");
+    }
+
+    #[test]
+    fn resolve_without_path_opens_instance_url() {
+        let instances = [instance("club", "https://chat.example.org/club")];
+        let (_, url) = resolve(&link("chatto://chat.example.org"), &instances, "club").unwrap();
+        assert_eq!(url.as_str(), "https://chat.example.org/club");
+    }
+
+    #[test]
+    fn resolve_matches_port() {
+        let instances = [
+            instance("default", "https://chat.example.org"),
+            instance("custom", "https://chat.example.org:8443"),
+        ];
+        let (target, url) = resolve(
+            &link("chatto://chat.example.org:8443/chat/DM/r1"),
+            &instances,
+            "default",
+        )
+        .unwrap();
+        assert_eq!(target.id, "custom");
+        assert_eq!(url.as_str(), "https://chat.example.org:8443/chat/DM/r1");
+
+        let (target, _) = resolve(
+            &link("chatto://chat.example.org/chat/DM/r1"),
+            &instances,
+            "custom",
+        )
+        .unwrap();
+        assert_eq!(target.id, "default");
+    }
+
+    #[test]
+    fn resolve_rejects_unknown_host() {
+        let instances = [instance("work", "https://chat.example.org")];
+        let error = resolve(
+            &link("chatto://evil.example.org/chat/s1/r1"),
+            &instances,
+            "work",
+        )
+        .unwrap_err();
+        assert!(error.contains("evil.example.org"));
+    }
+
+    #[test]
+    fn resolve_rejects_missing_host() {
+        let instances = [instance("work", "https://chat.example.org")];
+        assert!(resolve(&link("chatto:///chat/s1/r1"), &instances, "work").is_err());
+        assert!(resolve(&link("chatto:chat/s1/r1"), &instances, "work").is_err());
+    }
+
+    #[test]
+    fn resolve_rejects_other_schemes() {
+        let instances = [instance("work", "https://chat.example.org")];
+        let error = resolve(
+            &link("https://chat.example.org/chat/s1/r1"),
+            &instances,
+            "work",
+        )
+        .unwrap_err();
+        assert!(error.contains("https"));
+    }
+
+    #[test]
+    fn invite_goes_to_settings_confirmation() {
+        let invite = link("chatto://add-instance?url=https://acme.example.org&name=%20Acme%20");
+        assert!(is_invite(&invite));
+        assert!(!is_invite(&link("chatto://chat.example.org/chat/s1/r1")));
+
+        let url = invite_url(&invite).unwrap();
+        assert_eq!(url.path(), "/");
+        let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert_eq!(
+            query,
+            [
+                ("settings".to_string(), String::new()),
+                (
+                    "add_url".to_string(),
+                    "https://acme.example.org/".to_string()
+                ),
+                ("add_name".to_string(), "Acme".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn invite_needs_http_server_url() {
+        assert!(invite_url(&link("chatto://add-instance?name=Acme")).is_err());
+        assert!(invite_url(&link("chatto://add-instance?url=ftp://acme.example.org")).is_err());
+        assert!(invite_url(&link("chatto://add-instance?url=not%20a%20url")).is_err());
+    }
+}
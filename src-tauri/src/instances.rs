@@ -0,0 +1,255 @@
+// Stored list of Chatto instances the user can switch between.
+//
+// config.json keeps the list under "instances" and the id of the one in use
+// under "active_instance". The active instance's URL is also mirrored to the
+// legacy "server_url" key: stores written before multi-instance support only
+// have that key (settings::migrate seeds the list from it), and the Android
+// NotificationService still reads it directly.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::settings::{self, Settings};
+use crate::DEFAULT_SERVER_URL;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Instance {
+    pub id: String,
+    pub label: String,
+    pub url: String,
+    /// Keep using the webview's default data store instead of a per-instance
+    /// one. Only set on the instance seeded from a pre-multi-instance store,
+    /// so upgrading doesn't log the user out.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub default_data_store: bool,
+}
+
+// Instance given with --server-url: active for this launch only, never saved.
+static OVERRIDE: Mutex<Option<Instance>> = Mutex::new(None);
+
+struct Instances {
+    list: Vec<Instance>,
+    active: String,
+}
+
+impl Instances {
+    fn active(&self) -> &Instance {
+        self.list
+            .iter()
+            .find(|i| i.id == self.active)
+            .unwrap_or(&self.list[0])
+    }
+}
+
+pub fn new_id() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("{nanos:x}")
+}
+
+/// Default label for an instance: the host part of its URL.
+pub fn label_for(url: &str) -> String {
+    url.parse::<tauri::Url>()
+        .ok()
+        .and_then(|u| u.host_str().map(String::from))
+        .unwrap_or_else(|| url.to_string())
+}
+
+fn from_settings(settings: &Settings) -> Result<Instances, String> {
+    // settings::migrate seeds the list at startup, so an empty one means the
+    // store couldn't be read or migrated.
+    let list = settings.instances.clone();
+    if list.is_empty() {
+        return Err("No instances configured".into());
+    }
+    let active = settings
+        .active_instance
+        .clone()
+        .filter(|id| list.iter().any(|i| &i.id == id))
+        .unwrap_or_else(|| list[0].id.clone());
+
+    Ok(Instances { list, active })
+}
+
+fn load(app: &tauri::AppHandle) -> Result<Instances, String> {
+    from_settings(&settings::load(app)?)
+}
+
+/// Change the stored instances. Reading and writing back both happen under
+/// the settings write lock, so concurrent changes can't overwrite each other.
+fn modify<T>(
+    app: &tauri::AppHandle,
+    change: impl FnOnce(&mut Instances) -> Result<T, String>,
+) -> Result<T, String> {
+    settings::update(app, |settings| {
+        let mut instances = from_settings(settings)?;
+        let result = change(&mut instances)?;
+        settings.server_url =
+            Some(instances.active().url.clone()).filter(|url| url != DEFAULT_SERVER_URL);
+        settings.instances = instances.list;
+        settings.active_instance = Some(instances.active);
+        Ok(result)
+    })?
+}
+
+pub fn list(app: &tauri::AppHandle) -> Vec<Instance> {
+    load(app).map(|i| i.list).unwrap_or_default()
+}
+
+/// The instance the main window should show. Falls back to the default
+/// server when the store is unreadable.
+pub fn active(app: &tauri::AppHandle) -> Instance {
+    if let Some(instance) = OVERRIDE.lock().ok().and_then(|o| o.clone()) {
+        return instance;
+    }
+    load(app)
+        .map(|i| i.active().clone())
+        .unwrap_or_else(|_| Instance {
+            id: String::new(),
+            label: label_for(DEFAULT_SERVER_URL),
+            url: DEFAULT_SERVER_URL.to_string(),
+            default_data_store: true,
+        })
+}
+
+/// Show `url` for the rest of this launch without touching the stored list.
+/// A stored instance on the same origin lends its id, and with it its webview
+/// session.
+pub fn set_override(app: &tauri::AppHandle, url: &str) {
+    let origin = |u: &str| u.parse::<tauri::Url>().ok().map(|u| u.origin());
+    let instance = list(app)
+        .into_iter()
+        .find(|i| origin(&i.url).is_some() && origin(&i.url) == origin(url))
+        .map(|i| Instance {
+            url: url.to_string(),
+            ..i
+        })
+        .unwrap_or_else(|| Instance {
+            id: "cli".into(),
+            label: label_for(url),
+            url: url.to_string(),
+            default_data_store: false,
+        });
+    if let Ok(mut o) = OVERRIDE.lock() {
+        *o = Some(instance);
+    }
+}
+
+fn clear_override() {
+    if let Ok(mut o) = OVERRIDE.lock() {
+        *o = None;
+    }
+}
+
+pub fn add(app: &tauri::AppHandle, label: &str, url: &str) -> Result<Instance, String> {
+    let label = label.trim();
+    let instance = Instance {
+        id: new_id(),
+        label: if label.is_empty() {
+            label_for(url)
+        } else {
+            label.to_string()
+        },
+        url: url.to_string(),
+        default_data_store: false,
+    };
+    modify(app, |instances| {
+        instances.list.push(instance.clone());
+        Ok(())
+    })?;
+    Ok(instance)
+}
+
+pub fn remove(app: &tauri::AppHandle, id: &str) -> Result<(), String> {
+    modify(app, |instances| {
+        if instances.list.len() == 1 {
+            return Err("Cannot remove the only instance".into());
+        }
+        instances.list.retain(|i| i.id != id);
+        if instances.active == id {
+            instances.active = instances.list[0].id.clone();
+        }
+        Ok(())
+    })
+}
+
+pub fn set_active(app: &tauri::AppHandle, id: &str) -> Result<Instance, String> {
+    let instance = modify(app, |instances| {
+        let instance = instances
+            .list
+            .iter()
+            .find(|i| i.id == id)
+            .cloned()
+            .ok_or("Unknown instance")?;
+        instances.active = instance.id.clone();
+        Ok(instance)
+    })?;
+    clear_override();
+    Ok(instance)
+}
+
+/// Point the active instance at a new URL (the settings page "Server" field).
+/// The label follows the URL unless the user gave it a custom one.
+pub fn set_active_url(app: &tauri::AppHandle, url: &str) -> Result<(), String> {
+    modify(app, |instances| {
+        let active = instances.active.clone();
+        if let Some(instance) = instances.list.iter_mut().find(|i| i.id == active) {
+            if instance.label == label_for(&instance.url) {
+                instance.label = label_for(url);
+            }
+            instance.url = url.to_string();
+        }
+        Ok(())
+    })?;
+    clear_override();
+    Ok(())
+}
+
+/// Directory holding the webview profile (cookies, localStorage, service
+/// workers) of each instance that has its own data store.
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+pub fn webview_data_root(app: &tauri::AppHandle) -> Option<std::path::PathBuf> {
+    use tauri::Manager;
+    let dir = app.path().app_local_data_dir().ok()?;
+    Some(match crate::profile() {
+        Some(profile) => dir.join("profiles").join(profile).join("webview"),
+        None => dir.join("webview"),
+    })
+}
+
+/// Remove profile directories of instances that no longer exist. Runs at
+/// startup, before any webview has one of them open. Does nothing unless the
+/// instance list could be read, so an unreadable store never costs the user
+/// their sessions.
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+pub fn prune_webview_data(app: &tauri::AppHandle) -> Result<(), String> {
+    let Some(root) = webview_data_root(app) else {
+        return Ok(());
+    };
+    let Ok(entries) = std::fs::read_dir(&root) else {
+        return Ok(());
+    };
+    // load fails on an empty list too
+    let ids: Vec<String> = load(app)?.list.into_iter().map(|i| i.id).collect();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !ids.contains(&name) {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// WKWebView identifies persistent data stores by UUID. Derive one from the
+/// instance id with FNV-1a so it is stable across launches and Rust versions.
+#[cfg(target_os = "macos")]
+pub fn data_store_identifier(id: &str) -> [u8; 16] {
+    let mut hash: u128 = 0x6c62272e07bb014262b821756295c58d;
+    for byte in id.bytes() {
+        hash ^= byte as u128;
+        hash = hash.wrapping_mul(0x0000000001000000000000000000013b);
+    }
+    hash.to_be_bytes()
+}
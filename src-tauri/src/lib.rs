@@ -1,17 +1,38 @@
+#[cfg(desktop)]
+mod badge;
+mod backups;
+#[cfg(desktop)]
+mod cli;
+mod deep_link;
+mod instances;
+mod notifications;
+mod quiet_hours;
+#[cfg(desktop)]
+mod policy;
+mod settings;
+#[cfg(desktop)]
+mod subscription;
+#[cfg(desktop)]
+mod tray_host;
+#[cfg(all(desktop, feature = "updater"))]
+mod update_file;
+#[cfg(all(desktop, feature = "updater"))]
+mod updates;
+
 use tauri::Manager;
 use tauri_plugin_deep_link::DeepLinkExt;
-use tauri_plugin_store::StoreExt;
 
 #[cfg(desktop)]
+use tauri::menu::{AboutMetadataBuilder, Menu, MenuItem, PredefinedMenuItem, Submenu};
+#[cfg(all(desktop, feature = "tray"))]
 use tauri::{
-    menu::{AboutMetadataBuilder, CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
+    menu::CheckMenuItem,
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
 };
 use tauri::{WebviewUrl, WebviewWindowBuilder};
 
-use serde_json::json;
 use std::sync::Mutex;
-use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 
 // Tracks the host that started an /instances/add OIDC flow. While set, all
 // navigations are kept inside the webview so the redirect chain can complete
@@ -19,7 +40,37 @@ use std::sync::atomic::{AtomicI32, Ordering};
 #[cfg(desktop)]
 static INSTANCE_FLOW_ORIGIN: Mutex<Option<String>> = Mutex::new(None);
 
-const DEFAULT_SERVER_URL: &str = "https://chat.chatto.run";
+// Profile selected with --profile, if any.
+#[cfg(desktop)]
+fn profile() -> Option<&'static str> {
+    cli::args().profile.as_deref()
+}
+
+#[cfg(mobile)]
+fn profile() -> Option<&'static str> {
+    None
+}
+
+// Settings store path, relative to the app data directory. Each --profile
+// keeps its own under profiles/<name>/.
+fn config_file() -> std::path::PathBuf {
+    match profile() {
+        Some(profile) => std::path::Path::new("profiles").join(profile).join("config.json"),
+        None => "config.json".into(),
+    }
+}
+
+// Page the next main window should open instead of its instance's root,
+// e.g. the room a deep link pointed at.
+static START_URL: Mutex<Option<tauri::Url>> = Mutex::new(None);
+
+// Branding, set at build time (see build.rs)
+const DEFAULT_SERVER_URL: &str = env!("CHATTO_DEFAULT_SERVER_URL");
+const PRODUCT_NAME: &str = env!("CHATTO_PRODUCT_NAME");
+#[cfg(desktop)]
+const WEBSITE_URL: &str = env!("CHATTO_WEBSITE_URL");
+#[cfg(desktop)]
+const WEBSITE_LABEL: &str = env!("CHATTO_WEBSITE_LABEL");
 
 const NOTIFICATION_BRIDGE_JS: &str = r#"
 (function() {
@@ -40,6 +91,41 @@ const NOTIFICATION_BRIDGE_JS: &str = r#"
         });
     } catch(e) {}
 
+    // Keep Notification API mock for compatibility — reported as granted so
+    // the web app does not prompt the user for permission.
+    window.Notification = function(title, options) {
+        if (window.__TAURI_INTERNALS__) {
+            window.__TAURI_INTERNALS__.invoke('show_notification', {
+                title: title,
+                body: (options && options.body) || ''
+            }).catch(function() {});
+        }
+        this.title = title;
+        this.body = (options && options.body) || '';
+        this.icon = (options && options.icon) || '';
+        this.tag = (options && options.tag) || '';
+        this.onclick = null;
+        this.onclose = null;
+        this.onerror = null;
+        this.onshow = null;
+        this.close = function() {};
+    };
+    window.Notification.permission = 'granted';
+    window.Notification.requestPermission = function() {
+        return Promise.resolve('granted');
+    };
+})();
+"#;
+
+// On mobile the web app's own graphql-ws socket is the notification source:
+// read its myInstanceEvents stream and raise native notifications from it.
+// Desktop runs its own subscription in Rust (see subscription.rs).
+#[cfg(mobile)]
+const WEBSOCKET_NOTIFICATION_JS: &str = r#"
+(function() {
+    if (window.__chattoWebSocketBridged) return;
+    window.__chattoWebSocketBridged = true;
+
     // Deduplication: track recently fired notifications by key (roomId or notificationId)
     // to avoid firing twice when multiple WebSocket connections deliver the same event.
     var __chattoRecentNotifKeys = {};
@@ -50,6 +136,10 @@ const NOTIFICATION_BRIDGE_JS: &str = r#"
         return true;
     }
 
+    // Rooms with a recent MentionNotificationEvent, so quiet hours can let
+    // mentions through
+    var __chattoMentionedRooms = {};
+
     // Fetch the latest room event and show a native notification with the
     // actual message body. The /api/graphql endpoint is same-origin so the
     // request carries the user's session cookies automatically.
@@ -73,9 +163,13 @@ const NOTIFICATION_BRIDGE_JS: &str = r#"
             var actor = ev.actor && ev.actor.displayName;
             var body = ev.event && ev.event.body;
             if (!body) return; // not a message event (e.g. join/leave)
+            var mentionedAt = __chattoMentionedRooms[roomId];
             window.__TAURI_INTERNALS__.invoke('show_notification', {
                 title: actor || 'Chatto',
-                body: body
+                body: body,
+                spaceId: spaceId,
+                roomId: roomId,
+                mention: !!mentionedAt && Date.now() - mentionedAt < 10000
             }).catch(function() {});
         })
         .catch(function() {});
@@ -97,11 +191,15 @@ const NOTIFICATION_BRIDGE_JS: &str = r#"
                         if (!events || !events.event) return;
                         var e = events.event;
                         var type = e.__typename;
-                        if (!window.__chattoWindowHidden) return;
+                        // Only the room on screen is already being read
+                        if (!window.__chattoWindowHidden && e.roomId
+                            && e.roomId === window.__chattoActiveRoom) return;
                         if (type === 'NotificationCreatedEvent' && e.roomId) {
                             // spaceId is "DM" for direct messages, a real ID for space rooms
                             __chattoFetchRoomAndNotify(e.spaceId || 'DM', e.roomId);
                         } else if (type === 'MentionNotificationEvent') {
+                            var mentionedRoom = e.roomId || (e.room && e.room.id);
+                            if (mentionedRoom) __chattoMentionedRooms[mentionedRoom] = Date.now();
                             // Mentions fire alongside NotificationCreatedEvent; let that handle it
                             // to avoid duplicates. Keep this as fallback if spaceId is missing.
                             if (!e.spaceId && window.__TAURI_INTERNALS__) {
@@ -109,7 +207,8 @@ const NOTIFICATION_BRIDGE_JS: &str = r#"
                                     title: (e.space && e.space.name) || 'Chatto',
                                     body: (e.mentionedBy && e.mentionedBy.displayName || 'Someone')
                                         + ' mentioned you in #'
-                                        + (e.room && e.room.name || 'a room')
+                                        + (e.room && e.room.name || 'a room'),
+                                    mention: true
                                 }).catch(function() {});
                             }
                         }
@@ -126,33 +225,12 @@ const NOTIFICATION_BRIDGE_JS: &str = r#"
         window.WebSocket = PatchedWebSocket;
     })();
 
-    // Keep Notification API mock for compatibility — reported as granted so
-    // the web app does not prompt the user for permission.
-    window.Notification = function(title, options) {
-        if (window.__TAURI_INTERNALS__) {
-            window.__TAURI_INTERNALS__.invoke('show_notification', {
-                title: title,
-                body: (options && options.body) || ''
-            }).catch(function() {});
-        }
-        this.title = title;
-        this.body = (options && options.body) || '';
-        this.icon = (options && options.icon) || '';
-        this.tag = (options && options.tag) || '';
-        this.onclick = null;
-        this.onclose = null;
-        this.onerror = null;
-        this.onshow = null;
-        this.close = function() {};
-    };
-    window.Notification.permission = 'granted';
-    window.Notification.requestPermission = function() {
-        return Promise.resolve('granted');
-    };
 })();
 "#;
 
-#[cfg(target_os = "android")]
+// Reports the room on screen so notifications for it can be dropped while
+// the window has focus: to Rust (show_notification, the desktop
+// subscription) and, on Android, to the notification service directly.
 const ACTIVE_ROOM_TRACKER_JS: &str = r#"
 (function() {
     if (window.__chattoRoomTracker) return;
@@ -161,7 +239,14 @@ const ACTIVE_ROOM_TRACKER_JS: &str = r#"
     function reportRoom() {
         var m = window.location.pathname.match(/^\/chat\/[^\/]+\/([^\/]+)/);
         var roomId = m ? m[1] : '';
-        // Call Android JavascriptInterface directly — no Rust IPC needed
+        if (roomId === window.__chattoActiveRoom) return;
+        window.__chattoActiveRoom = roomId;
+        if (window.__TAURI_INTERNALS__) {
+            window.__TAURI_INTERNALS__.invoke('set_active_room', {
+                roomId: roomId || null
+            }).catch(function() {});
+        }
+        // Android JavascriptInterface, read by the background service
         if (window.ChattoAndroid && window.ChattoAndroid.setActiveRoom) {
             window.ChattoAndroid.setActiveRoom(roomId);
         }
@@ -284,9 +369,14 @@ const EXTERNAL_LINK_JS: &str = r#"
 "#;
 
 // Template icon for macOS menu bar (black on transparent, used as template image)
-#[cfg(desktop)]
+#[cfg(all(desktop, feature = "tray"))]
 const TRAY_ICON_BYTES: &[u8] = include_bytes!("../icons/tray-icon.png");
 
+// Set while the main window is being torn down to be rebuilt for another
+// instance; the Destroyed handler recreates it once the old one is gone.
+#[cfg(desktop)]
+static REOPEN_MAIN_WINDOW: AtomicBool = AtomicBool::new(false);
+
 // Zoom level stored as percentage (100 = 100%). Step is 10%.
 #[cfg(desktop)]
 static ZOOM_LEVEL: AtomicI32 = AtomicI32::new(100);
@@ -306,46 +396,101 @@ fn apply_zoom(window: &tauri::WebviewWindow, delta: i32) {
         (prev + delta).clamp(30, 300)
     };
     let _ = window.set_zoom(level as f64 / 100.0);
-    if let Ok(store) = window.app_handle().store("config.json") {
-        store.set("zoom_level", json!(level));
-        let _ = store.save();
+    if let Err(e) = settings::update(window.app_handle(), |s| s.zoom_level = level) {
+        eprintln!("failed to save zoom level: {e}");
     }
 }
 
-#[tauri::command]
-fn set_server_url(app: tauri::AppHandle, url: String) -> Result<(), String> {
-    let parsed: tauri::Url = url.parse().map_err(|e| format!("Invalid URL: {e}"))?;
-
+fn check_server_reachable(parsed: &tauri::Url) -> Result<(), String> {
     // Skip reachability check for localhost (may use self-signed certs)
     let is_localhost = parsed
         .host_str()
         .map(|h| h == "localhost" || h == "127.0.0.1" || h == "::1")
         .unwrap_or(false);
+    if is_localhost {
+        return Ok(());
+    }
 
-    if !is_localhost {
-        match ureq::head(parsed.as_str())
-            .timeout(std::time::Duration::from_secs(10))
-            .call()
-        {
-            Ok(_) => {}
-            Err(ureq::Error::Status(_, _)) => {
-                // Any HTTP response means the server is reachable
-            }
-            Err(ureq::Error::Transport(e)) => {
-                let reason = match e.kind() {
-                    ureq::ErrorKind::Dns => "Server not found — check the address",
-                    ureq::ErrorKind::ConnectionFailed => "Could not connect to server",
-                    ureq::ErrorKind::Io => "Connection error",
-                    _ => "Server unreachable",
-                };
-                return Err(format!("{reason} ({e})"));
-            }
+    match ureq::head(parsed.as_str())
+        .timeout(std::time::Duration::from_secs(10))
+        .call()
+    {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(_, _)) => {
+            // Any HTTP response means the server is reachable
+            Ok(())
+        }
+        Err(ureq::Error::Transport(e)) => {
+            let reason = match e.kind() {
+                ureq::ErrorKind::Dns => "Server not found — check the address",
+                ureq::ErrorKind::ConnectionFailed => "Could not connect to server",
+                ureq::ErrorKind::Io => "Connection error",
+                _ => "Server unreachable",
+            };
+            Err(format!("{reason} ({e})"))
         }
     }
+}
 
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
-    store.set("server_url", json!(url));
-    store.save().map_err(|e| e.to_string())?;
+// Show the active instance in the main window. On desktop every instance has
+// its own webview data store, which is fixed when the webview is created, so
+// the window is destroyed and rebuilt rather than navigated.
+#[cfg(desktop)]
+fn open_active_instance(app: &tauri::AppHandle) -> Result<(), String> {
+    let Some(window) = app.get_webview_window("main") else {
+        return create_main_window(app, true).map_err(|e| e.to_string());
+    };
+    REOPEN_MAIN_WINDOW.store(true, Ordering::SeqCst);
+    subscription::restart();
+    badge::clear(app);
+    window.destroy().map_err(|e| e.to_string())
+}
+
+#[cfg(mobile)]
+fn open_active_instance(app: &tauri::AppHandle) -> Result<(), String> {
+    let url = take_start_url(&instances::active(app))?;
+    let window = app.get_webview_window("main").ok_or("no main window")?;
+    window.navigate(url).map_err(|e| e.to_string())
+}
+
+fn set_start_url(url: tauri::Url) {
+    if let Ok(mut start) = START_URL.lock() {
+        *start = Some(url);
+    }
+}
+
+// The URL to open for `instance`: a pending start URL on the same origin (or
+// the bundled settings page), or the instance's own URL.
+fn take_start_url(instance: &instances::Instance) -> Result<tauri::Url, String> {
+    let url: tauri::Url = instance.url.parse().map_err(|e| format!("Invalid URL: {e}"))?;
+    let start = START_URL.lock().ok().and_then(|mut s| s.take());
+    // Compared field-wise: tauri:// has an opaque origin, which never equals
+    // another one.
+    #[cfg(desktop)]
+    let is_local = |s: &tauri::Url| {
+        let local = frontend_url("/");
+        s.scheme() == local.scheme() && s.host_str() == local.host_str() && s.port() == local.port()
+    };
+    #[cfg(mobile)]
+    let is_local = |_: &tauri::Url| false;
+    Ok(start
+        .filter(|s| s.origin() == url.origin() || is_local(s))
+        .unwrap_or(url))
+}
+
+#[tauri::command]
+fn set_server_url(app: tauri::AppHandle, url: String) -> Result<(), String> {
+    let parsed: tauri::Url = url.parse().map_err(|e| format!("Invalid URL: {e}"))?;
+    #[cfg(desktop)]
+    policy::check_url(&url)?;
+    check_server_reachable(&parsed)?;
+
+    instances::set_active_url(&app, &url)?;
+    #[cfg(desktop)]
+    {
+        refresh_tray_menu(&app);
+        subscription::restart();
+    }
 
     let window = app.get_webview_window("main").ok_or("no main window")?;
     window.navigate(parsed).map_err(|e| e.to_string())
@@ -354,9 +499,10 @@ fn set_server_url(app: tauri::AppHandle, url: String) -> Result<(), String> {
 #[cfg(desktop)]
 #[tauri::command]
 fn clear_server_url(app: tauri::AppHandle) -> Result<(), String> {
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
-    store.delete("server_url");
-    store.save().map_err(|e| e.to_string())?;
+    policy::check_url(DEFAULT_SERVER_URL)?;
+    instances::set_active_url(&app, DEFAULT_SERVER_URL)?;
+    refresh_tray_menu(&app);
+    subscription::restart();
 
     let window = app.get_webview_window("main").ok_or("no main window")?;
     let default_url: tauri::Url = DEFAULT_SERVER_URL.parse().expect("invalid DEFAULT_SERVER_URL");
@@ -365,10 +511,55 @@ fn clear_server_url(app: tauri::AppHandle) -> Result<(), String> {
 
 #[tauri::command]
 fn get_server_url(app: tauri::AppHandle) -> Result<Option<String>, String> {
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
-    Ok(store
-        .get("server_url")
-        .and_then(|v| v.as_str().map(|s| s.to_string())))
+    let url = instances::active(&app).url;
+    Ok(Some(url).filter(|u| u != DEFAULT_SERVER_URL))
+}
+
+#[tauri::command]
+fn get_instances(app: tauri::AppHandle) -> Vec<instances::Instance> {
+    instances::list(&app)
+}
+
+#[tauri::command]
+fn get_active_instance(app: tauri::AppHandle) -> instances::Instance {
+    instances::active(&app)
+}
+
+#[tauri::command]
+fn add_instance(app: tauri::AppHandle, label: String, url: String) -> Result<instances::Instance, String> {
+    let parsed: tauri::Url = url.parse().map_err(|e| format!("Invalid URL: {e}"))?;
+    #[cfg(desktop)]
+    policy::check_url(&url)?;
+    check_server_reachable(&parsed)?;
+
+    let instance = instances::add(&app, &label, &url)?;
+    #[cfg(desktop)]
+    refresh_tray_menu(&app);
+    Ok(instance)
+}
+
+#[tauri::command]
+fn remove_instance(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    let was_active = instances::active(&app).id == id;
+    instances::remove(&app, &id)?;
+    #[cfg(desktop)]
+    refresh_tray_menu(&app);
+    if was_active {
+        open_active_instance(&app)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn switch_instance(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    #[cfg(desktop)]
+    if let Some(instance) = instances::list(&app).iter().find(|i| i.id == id) {
+        policy::check_url(&instance.url)?;
+    }
+    instances::set_active(&app, &id)?;
+    #[cfg(desktop)]
+    refresh_tray_menu(&app);
+    open_active_instance(&app)
 }
 
 // Tracks an active /instances/add OIDC chain. Called by the injected JS on
@@ -404,40 +595,111 @@ fn check_instance_flow(url: String) -> Result<bool, String> {
 }
 
 #[tauri::command]
-fn show_notification(app: tauri::AppHandle, title: String, body: String) -> Result<(), String> {
-    // Check if notifications are enabled
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
-    let enabled = store
-        .get("notifications_enabled")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(true);
-    if !enabled {
+fn show_notification(
+    app: tauri::AppHandle,
+    title: String,
+    body: String,
+    space_id: Option<String>,
+    room_id: Option<String>,
+    mention: Option<bool>,
+) -> Result<(), String> {
+    if !get_notifications_enabled(app.clone())? {
+        return Ok(());
+    }
+    // The user is reading this room already; other rooms still notify while
+    // the window has focus
+    if room_id
+        .as_deref()
+        .is_some_and(|room_id| notifications::on_screen(&app, room_id))
+    {
+        return Ok(());
+    }
+    let direct = space_id.as_deref() == Some("DM");
+    let mention = mention.unwrap_or(false) || room_id.as_deref().is_some_and(quiet_hours::mentioned);
+    if quiet_hours::hold(&app, &title, direct, mention) {
         return Ok(());
     }
 
-    use tauri_plugin_notification::NotificationExt;
-    app.notification()
-        .builder()
-        .title(&title)
-        .body(&body)
-        .show()
-        .map_err(|e| e.to_string())
+    // Message notifications from the bridge carry their room so a click can
+    // navigate there; Notification API calls from the web app don't.
+    let target = space_id
+        .zip(room_id)
+        .map(|(space_id, room_id)| notifications::RoomTarget {
+            server_url: instances::active(&app).url,
+            space_id,
+            room_id,
+        });
+    notifications::show(&app, &title, &body, target)
+}
+
+// Room on screen in the main window, reported by ACTIVE_ROOM_TRACKER_JS.
+#[tauri::command]
+fn set_active_room(room_id: Option<String>) {
+    notifications::set_active_room(room_id);
+}
+
+// Hook for the web app to report exact unread/mention counts; overrides the
+// count kept from background notifications.
+#[cfg(desktop)]
+#[tauri::command]
+fn set_unread_count(app: tauri::AppHandle, unread: u32, mentions: Option<u32>) {
+    badge::set(&app, unread, mentions.unwrap_or(0));
+}
+
+#[cfg(desktop)]
+#[tauri::command]
+fn get_reply_context() -> Option<notifications::ReplyContext> {
+    notifications::reply_context()
+}
+
+#[cfg(desktop)]
+#[tauri::command]
+fn send_reply(app: tauri::AppHandle, body: String) -> Result<(), String> {
+    notifications::send_reply(&app, &body)
+}
+
+#[cfg(desktop)]
+#[tauri::command]
+fn cancel_reply(app: tauri::AppHandle) {
+    notifications::close_reply_window(&app);
+}
+
+// Called by the fetch that notification actions inject into the main
+// webview when the server rejects the request.
+#[cfg(desktop)]
+#[tauri::command]
+fn report_notification_failure(app: tauri::AppHandle, title: String, reason: String) {
+    notifications::report_failure(&app, &title, &reason);
 }
 
 #[tauri::command]
 fn get_notifications_enabled(app: tauri::AppHandle) -> Result<bool, String> {
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
-    Ok(store
-        .get("notifications_enabled")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(true))
+    #[cfg(desktop)]
+    if let Some(forced) = policy::get().notifications_enabled {
+        return Ok(forced);
+    }
+    Ok(settings::load(&app)?.notifications_enabled)
+}
+
+// Weekly quiet hours schedule consulted by show_notification.
+#[tauri::command]
+fn get_quiet_hours(app: tauri::AppHandle) -> Result<quiet_hours::QuietHours, String> {
+    Ok(settings::load(&app)?.quiet_hours)
+}
+
+#[tauri::command]
+fn set_quiet_hours(app: tauri::AppHandle, schedule: quiet_hours::QuietHours) -> Result<(), String> {
+    schedule.validate()?;
+    settings::update(&app, |s| s.quiet_hours = schedule)
 }
 
 #[tauri::command]
 fn set_notifications_enabled(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
-    let store = app.store("config.json").map_err(|e| e.to_string())?;
-    store.set("notifications_enabled", json!(enabled));
-    store.save().map_err(|e| e.to_string())
+    #[cfg(desktop)]
+    if policy::locks().notifications {
+        return Err("Notifications are managed by your administrator".into());
+    }
+    settings::update(&app, |s| s.notifications_enabled = enabled)
 }
 
 #[tauri::command]
@@ -469,16 +731,19 @@ fn open_external_url(app: tauri::AppHandle, url: String) -> Result<(), String> {
         .map_err(|e| e.to_string())
 }
 
-#[cfg(desktop)]
+#[cfg(all(desktop, feature = "autostart"))]
 #[tauri::command]
 fn get_autostart_enabled(app: tauri::AppHandle) -> Result<bool, String> {
     use tauri_plugin_autostart::ManagerExt;
     Ok(app.autolaunch().is_enabled().unwrap_or(false))
 }
 
-#[cfg(desktop)]
+#[cfg(all(desktop, feature = "autostart"))]
 #[tauri::command]
 fn set_autostart_enabled(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    if policy::get().hide_autostart {
+        return Err("Start at login is managed by your administrator".into());
+    }
     use tauri_plugin_autostart::ManagerExt;
     let autolaunch = app.autolaunch();
     if enabled {
@@ -488,79 +753,283 @@ fn set_autostart_enabled(app: tauri::AppHandle, enabled: bool) -> Result<(), Str
     }
 }
 
+// Save settings (and start at login) to a file the user picks, to set up
+// Chatto the same way elsewhere. Returns None when the dialog is cancelled.
 #[cfg(desktop)]
-async fn do_update_check(app: tauri::AppHandle, silent: bool) {
-    use tauri_plugin_notification::NotificationExt;
-    use tauri_plugin_updater::UpdaterExt;
+#[tauri::command]
+async fn export_settings(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    #[cfg(feature = "autostart")]
+    let autostart = get_autostart_enabled(app.clone()).ok();
+    #[cfg(not(feature = "autostart"))]
+    let autostart = None;
+    let export = settings::Export::new(settings::load(&app)?, autostart);
+    let Some(path) = app
+        .dialog()
+        .file()
+        .add_filter(&format!("{PRODUCT_NAME} settings"), &["json"])
+        .set_file_name("chatto-settings.json")
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = path.into_path().map_err(|e| e.to_string())?;
+    let contents = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+    std::fs::write(&path, contents)
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(Some(path.display().to_string()))
+}
 
-    let updater = match app.updater() {
-        Ok(u) => u,
-        Err(e) => {
-            if !silent {
-                let _ = app
-                    .notification()
-                    .builder()
-                    .title("Update check failed")
-                    .body(&e.to_string())
-                    .show();
-            }
-            return;
-        }
+// Replace the settings with ones from an exported file and show the
+// imported server. Returns false when the dialog is cancelled.
+#[cfg(desktop)]
+#[tauri::command]
+async fn import_settings(app: tauri::AppHandle) -> Result<bool, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let Some(path) = app
+        .dialog()
+        .file()
+        .add_filter(&format!("{PRODUCT_NAME} settings"), &["json"])
+        .blocking_pick_file()
+    else {
+        return Ok(false);
     };
+    let path = path.into_path().map_err(|e| e.to_string())?;
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let export = settings::Export::parse(&contents)?;
+    for instance in &export.settings.instances {
+        policy::check_url(&instance.url)?;
+    }
 
-    match updater.check().await {
-        Ok(Some(update)) => {
-            if silent {
-                let _ = app
-                    .notification()
-                    .builder()
-                    .title("Chatto update available")
-                    .body(&format!(
-                        "v{} is ready — use Chatto > Check for Updates to install",
-                        update.version
-                    ))
-                    .show();
-            } else {
-                let _ = app
-                    .notification()
-                    .builder()
-                    .title(&format!("Downloading Chatto {}…", update.version))
-                    .body("Chatto will restart when the update is ready.")
-                    .show();
-                match update.download_and_install(|_, _| {}, || {}).await {
-                    Ok(_) => app.restart(),
-                    Err(e) => {
-                        let _ = app
-                            .notification()
-                            .builder()
-                            .title("Update failed")
-                            .body(&e.to_string())
-                            .show();
-                    }
-                }
-            }
-        }
-        Ok(None) => {
-            if !silent {
-                let _ = app
-                    .notification()
-                    .builder()
-                    .title("Chatto is up to date")
-                    .body(&format!("v{} is the latest version.", app.package_info().version))
-                    .show();
-            }
-        }
-        Err(e) => {
-            if !silent {
-                let _ = app
-                    .notification()
-                    .builder()
-                    .title("Update check failed")
-                    .body(&e.to_string())
-                    .show();
-            }
+    let previous = instances::active(&app);
+    settings::import(&app, export.settings)?;
+    #[cfg(feature = "autostart")]
+    if let Some(enabled) = export.autostart {
+        if let Err(e) = set_autostart_enabled(app.clone(), enabled) {
+            eprintln!("failed to apply imported start at login: {e}");
         }
     }
+    refresh_tray_menu(&app);
+
+    let level = settings::get(&app).zoom_level;
+    ZOOM_LEVEL.store(level, Ordering::SeqCst);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_zoom(level as f64 / 100.0);
+    }
+
+    // A different active instance has its own webview data store and needs
+    // the window rebuilt; otherwise re-apply its URL like the Server field.
+    let active = instances::active(&app);
+    if active.id != previous.id {
+        open_active_instance(&app)?;
+        return Ok(true);
+    }
+    set_server_url(app, active.url)
+        .map(|_| true)
+        .map_err(|e| format!("Settings were imported, but the server could not be opened: {e}"))
+}
+
+#[cfg(desktop)]
+#[tauri::command]
+fn get_start_minimized(app: tauri::AppHandle) -> Result<bool, String> {
+    Ok(settings::load(&app)?.start_minimized)
+}
+
+#[cfg(desktop)]
+#[tauri::command]
+fn set_start_minimized(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    settings::update(&app, |s| s.start_minimized = enabled)
+}
+
+// What closing the main window does: "tray" (hide), "minimize" or "quit".
+#[cfg(desktop)]
+#[tauri::command]
+fn get_close_behavior(app: tauri::AppHandle) -> Result<settings::CloseBehavior, String> {
+    Ok(settings::load(&app)?.close_behavior)
+}
+
+#[cfg(desktop)]
+#[tauri::command]
+fn set_close_behavior(app: tauri::AppHandle, behavior: settings::CloseBehavior) -> Result<(), String> {
+    settings::update(&app, |s| s.close_behavior = behavior)
+}
+
+#[cfg(all(desktop, feature = "updater"))]
+#[derive(serde::Serialize)]
+struct UpdateChannelInfo {
+    channel: settings::UpdateChannel,
+    // A prerelease stays installed after switching to stable until a newer
+    // stable release is out; the settings page says so.
+    running_prerelease: bool,
+}
+
+// Release channel the updater follows: "stable" or "beta".
+#[cfg(all(desktop, feature = "updater"))]
+#[tauri::command]
+fn get_update_channel(app: tauri::AppHandle) -> Result<UpdateChannelInfo, String> {
+    Ok(UpdateChannelInfo {
+        channel: settings::load(&app)?.update_channel,
+        running_prerelease: updates::running_prerelease(&app),
+    })
+}
+
+#[cfg(all(desktop, feature = "updater"))]
+#[tauri::command]
+fn set_update_channel(app: tauri::AppHandle, channel: settings::UpdateChannel) -> Result<(), String> {
+    if policy::get().disable_update_check {
+        return Err("Updates are managed by your administrator".into());
+    }
+    settings::update(&app, |s| s.update_channel = channel)
+}
+
+#[cfg(all(desktop, feature = "updater"))]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct UpdateSchedule {
+    enabled: bool,
+    interval_hours: u32,
+}
+
+// Background update checks: on/off and how many hours apart.
+#[cfg(all(desktop, feature = "updater"))]
+#[tauri::command]
+fn get_update_schedule(app: tauri::AppHandle) -> Result<UpdateSchedule, String> {
+    let settings = settings::load(&app)?;
+    Ok(UpdateSchedule {
+        enabled: settings.auto_update_check,
+        interval_hours: settings.update_check_interval_hours,
+    })
+}
+
+#[cfg(all(desktop, feature = "updater"))]
+#[tauri::command]
+fn set_update_schedule(app: tauri::AppHandle, schedule: UpdateSchedule) -> Result<(), String> {
+    if schedule.interval_hours == 0 {
+        return Err("The check interval must be at least an hour".into());
+    }
+    settings::update(&app, |s| {
+        s.auto_update_check = schedule.enabled;
+        s.update_check_interval_hours = schedule.interval_hours;
+    })
+}
+
+// Update manifests replacing the built-in release server; empty for the
+// default. The policy's list wins and can't be changed here.
+#[cfg(all(desktop, feature = "updater"))]
+#[tauri::command]
+fn get_update_endpoints(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    match &policy::get().update_endpoints {
+        Some(endpoints) => Ok(endpoints.clone()),
+        None => Ok(settings::load(&app)?.update_endpoints),
+    }
+}
+
+#[cfg(all(desktop, feature = "updater"))]
+#[tauri::command]
+fn set_update_endpoints(app: tauri::AppHandle, endpoints: Vec<String>) -> Result<(), String> {
+    if policy::get().update_endpoints.is_some() {
+        return Err("The update server is managed by your administrator".into());
+    }
+    let endpoints: Vec<String> = endpoints
+        .iter()
+        .map(|url| url.trim())
+        .filter(|url| !url.is_empty())
+        .map(String::from)
+        .collect();
+    for url in &endpoints {
+        settings::check_update_endpoint(url)?;
+    }
+    settings::update(&app, |s| s.update_endpoints = endpoints)
+}
+
+// The update window's offer: version, release notes, whether it is staged.
+#[cfg(all(desktop, feature = "updater"))]
+#[tauri::command]
+fn get_update_info(app: tauri::AppHandle) -> Option<updates::UpdateInfo> {
+    updates::info(&app)
+}
+
+// Download the offered update, then restart into it (`now`) or install it
+// when Chatto quits.
+#[cfg(all(desktop, feature = "updater"))]
+#[tauri::command]
+async fn install_update(app: tauri::AppHandle, now: bool) -> Result<(), String> {
+    updates::install(app, now).await
+}
+
+#[cfg(all(desktop, feature = "updater"))]
+#[tauri::command]
+fn close_update_window(app: tauri::AppHandle) {
+    updates::close_window(&app);
+}
+
+#[derive(serde::Serialize)]
+struct Branding {
+    product_name: &'static str,
+    default_server_url: &'static str,
+}
+
+// Build-time branding for the settings page.
+#[tauri::command]
+fn get_branding() -> Branding {
+    Branding {
+        product_name: PRODUCT_NAME,
+        default_server_url: DEFAULT_SERVER_URL,
+    }
+}
+
+// Optional parts this binary was built with (see [features] in Cargo.toml),
+// so the settings page can leave out what isn't there.
+#[cfg(desktop)]
+#[derive(serde::Serialize)]
+struct BuildFeatures {
+    updater: bool,
+    autostart: bool,
+    tray: bool,
+}
+
+#[cfg(desktop)]
+#[tauri::command]
+fn get_build_features() -> BuildFeatures {
+    BuildFeatures {
+        updater: cfg!(feature = "updater"),
+        autostart: cfg!(feature = "autostart"),
+        tray: cfg!(feature = "tray"),
+    }
+}
+
+// Settings the admin policy locks, shown read-only on the settings page.
+#[cfg(desktop)]
+#[tauri::command]
+fn get_policy_locks() -> policy::Locks {
+    policy::locks()
+}
+
+// Whether closing to the tray is possible right now. The settings page uses
+// it to explain why "Hide to tray" minimizes instead.
+#[cfg(desktop)]
+#[tauri::command]
+fn get_tray_available() -> bool {
+    tray_host::available()
+}
+
+// Tell the user once why closing minimized instead of hiding to the tray.
+#[cfg(all(desktop, feature = "tray"))]
+fn explain_missing_tray(app: &tauri::AppHandle) {
+    use tauri_plugin_notification::NotificationExt;
+
+    let first_time = settings::update(app, |s| !std::mem::replace(&mut s.tray_fallback_explained, true));
+    if first_time != Ok(true) {
+        return;
+    }
+    let _ = app
+        .notification()
+        .builder()
+        .title(&format!("{PRODUCT_NAME} was minimized"))
+        .body(&format!("Your desktop has no system tray, so closing the window minimizes {PRODUCT_NAME} instead of hiding it. Install a tray extension to hide it to the tray."))
+        .show();
 }
 
 #[cfg(desktop)]
@@ -587,14 +1056,21 @@ fn navigate_to_settings(app: &tauri::AppHandle) {
 }
 
 #[cfg(desktop)]
+fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+#[cfg(all(desktop, feature = "tray"))]
 fn toggle_window_visibility(app: &tauri::AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         if window.is_visible().unwrap_or(false) {
             let _ = window.hide();
         } else {
-            let _ = window.unminimize();
-            let _ = window.show();
-            let _ = window.set_focus();
+            show_main_window(app);
         }
     }
 }
@@ -603,15 +1079,13 @@ fn toggle_window_visibility(app: &tauri::AppHandle) {
 fn setup_app_menu(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
     let about_metadata = AboutMetadataBuilder::new()
         .version(Some(env!("GIT_VERSION")))
-        .website(Some("https://github.com/teal-bauer/chatto-tauri"))
-        .website_label(Some("GitHub"))
+        .website(Some(WEBSITE_URL))
+        .website_label(Some(WEBSITE_LABEL))
         .license(Some("AGPL-3.0"))
         .build();
 
-    let about = PredefinedMenuItem::about(app, Some("About Chatto"), Some(about_metadata))?;
+    let about = PredefinedMenuItem::about(app, Some(format!("About {PRODUCT_NAME}").as_str()), Some(about_metadata))?;
     let sep = PredefinedMenuItem::separator(app)?;
-    let check_updates = MenuItem::with_id(app, "menu_check_updates", "Check for Updates…", true, None::<&str>)?;
-    let sep_updates = PredefinedMenuItem::separator(app)?;
     let settings = MenuItem::with_id(app, "menu_settings", "Settings…", true, Some("CmdOrCtrl+,"))?;
     let quit = PredefinedMenuItem::quit(app, None)?;
 
@@ -623,21 +1097,31 @@ fn setup_app_menu(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
         let sep2 = PredefinedMenuItem::separator(app)?;
         let sep3 = PredefinedMenuItem::separator(app)?;
         Submenu::with_items(
-            app, "Chatto", true,
-            &[&about, &sep, &check_updates, &sep_updates, &settings, &sep2, &hide, &hide_others, &show_all, &sep3, &quit],
+            app, PRODUCT_NAME, true,
+            &[&about, &sep, &settings, &sep2, &hide, &hide_others, &show_all, &sep3, &quit],
         )?
     };
 
     #[cfg(not(target_os = "macos"))]
     let app_submenu = {
-        let github = MenuItem::with_id(app, "menu_github", "GitHub…", true, None::<&str>)?;
+        let github = MenuItem::with_id(app, "menu_github", format!("{WEBSITE_LABEL}…"), true, None::<&str>)?;
         let sep2 = PredefinedMenuItem::separator(app)?;
         Submenu::with_items(
-            app, "Chatto", true,
-            &[&about, &github, &sep, &check_updates, &sep_updates, &settings, &sep2, &quit],
+            app, PRODUCT_NAME, true,
+            &[&about, &github, &sep, &settings, &sep2, &quit],
         )?
     };
 
+    // Below About (and the website link) when the self-updater is built in
+    #[cfg(feature = "updater")]
+    {
+        let check_updates = MenuItem::with_id(app, "menu_check_updates", "Check for Updates…", true, None::<&str>)?;
+        let install_file = MenuItem::with_id(app, "menu_install_update_file", "Install Update from File…", true, None::<&str>)?;
+        let sep_updates = PredefinedMenuItem::separator(app)?;
+        let position = if cfg!(target_os = "macos") { 2 } else { 3 };
+        app_submenu.insert_items(&[&check_updates, &install_file, &sep_updates], position)?;
+    }
+
     let edit_submenu = Submenu::with_items(
         app,
         "Edit",
@@ -690,16 +1174,23 @@ fn setup_app_menu(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
 
     // Handle custom menu events
     app.on_menu_event(move |app, event| match event.id().as_ref() {
+        #[cfg(feature = "updater")]
         "menu_check_updates" => {
             let handle = app.clone();
-            tauri::async_runtime::spawn(do_update_check(handle, false));
+            tauri::async_runtime::spawn(updates::check(handle, false));
+        }
+        #[cfg(feature = "updater")]
+        "menu_install_update_file" => {
+            // File dialogs block, keep them off the main thread
+            let handle = app.clone();
+            std::thread::spawn(move || update_file::pick_and_install(&handle));
         }
         "menu_settings" => {
             navigate_to_settings(app);
         }
         "menu_github" => {
             use tauri_plugin_opener::OpenerExt;
-            let _ = app.opener().open_url("https://github.com/teal-bauer/chatto-tauri", None::<&str>);
+            let _ = app.opener().open_url(WEBSITE_URL, None::<&str>);
         }
         "menu_back" => {
             if let Some(window) = app.get_webview_window("main") {
@@ -737,38 +1228,77 @@ fn setup_app_menu(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-#[cfg(desktop)]
-fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
+#[cfg(all(desktop, feature = "tray"))]
+fn build_tray_menu(app: &tauri::AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
     let show_hide = MenuItem::with_id(app, "show_hide", "Show/Hide", true, None::<&str>)?;
     let settings = MenuItem::with_id(app, "settings", "Settings…", true, None::<&str>)?;
     let separator = PredefinedMenuItem::separator(app)?;
 
-    let autostart_enabled = {
-        use tauri_plugin_autostart::ManagerExt;
-        app.autolaunch().is_enabled().unwrap_or(false)
-    };
-    let autostart = CheckMenuItem::with_id(
-        app,
-        "autostart",
-        "Start at Login",
-        true,
-        autostart_enabled,
-        None::<&str>,
-    )?;
+    // Instance switcher: one checkable entry per stored instance, the active
+    // one checked. Rebuilt by refresh_tray_menu whenever the list changes.
+    let instances_submenu = Submenu::with_id(app, "instances", "Instances", true)?;
+    let active_id = instances::active(app).id;
+    for instance in instances::list(app) {
+        let item = CheckMenuItem::with_id(
+            app,
+            format!("instance:{}", instance.id),
+            &instance.label,
+            true,
+            instance.id == active_id,
+            None::<&str>,
+        )?;
+        instances_submenu.append(&item)?;
+    }
 
-    let quit = MenuItem::with_id(app, "quit", "Quit Chatto", true, None::<&str>)?;
     let menu = Menu::with_items(
         app,
-        &[&show_hide, &settings, &separator, &autostart, &separator, &quit],
+        &[&show_hide, &settings, &separator, &instances_submenu, &separator],
     )?;
 
+    #[cfg(feature = "autostart")]
+    if !policy::get().hide_autostart {
+        let autostart_enabled = {
+            use tauri_plugin_autostart::ManagerExt;
+            app.autolaunch().is_enabled().unwrap_or(false)
+        };
+        let autostart = CheckMenuItem::with_id(
+            app,
+            "autostart",
+            "Start at Login",
+            true,
+            autostart_enabled,
+            None::<&str>,
+        )?;
+        menu.append_items(&[&autostart, &PredefinedMenuItem::separator(app)?])?;
+    }
+
+    menu.append(&MenuItem::with_id(app, "quit", format!("Quit {PRODUCT_NAME}"), true, None::<&str>)?)?;
+    Ok(menu)
+}
+
+#[cfg(all(desktop, feature = "tray"))]
+fn refresh_tray_menu(app: &tauri::AppHandle) {
+    let Some(tray) = app.tray_by_id("main") else {
+        return;
+    };
+    if let Ok(menu) = build_tray_menu(app) {
+        let _ = tray.set_menu(Some(menu));
+    }
+}
+
+// Built without the tray: nothing to refresh
+#[cfg(all(desktop, not(feature = "tray")))]
+fn refresh_tray_menu(_app: &tauri::AppHandle) {}
+
+#[cfg(all(desktop, feature = "tray"))]
+fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
+    let menu = build_tray_menu(app.handle())?;
     let icon = tauri::image::Image::from_bytes(TRAY_ICON_BYTES)?;
 
-    let autostart_ref = autostart.clone();
-    TrayIconBuilder::new()
+    TrayIconBuilder::with_id("main")
         .icon(icon)
         .icon_as_template(true)
-        .tooltip("Chatto")
+        .tooltip(PRODUCT_NAME)
         .menu(&menu)
         .show_menu_on_left_click(false)
         .on_menu_event(move |app, event| match event.id.as_ref() {
@@ -776,6 +1306,7 @@ fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
             "settings" => {
                 navigate_to_settings(app);
             }
+            #[cfg(feature = "autostart")]
             "autostart" => {
                 use tauri_plugin_autostart::ManagerExt;
                 let autolaunch = app.autolaunch();
@@ -786,12 +1317,20 @@ fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
                     autolaunch.enable()
                 };
                 if result.is_err() {
-                    // Revert the auto-toggled checkbox state on failure
-                    let _ = autostart_ref.set_checked(was_enabled);
+                    // Rebuild to revert the auto-toggled checkbox state on failure
+                    refresh_tray_menu(app);
                 }
             }
             "quit" => app.exit(0),
-            _ => {}
+            other => {
+                if let Some(id) = other.strip_prefix("instance:") {
+                    // The clicked CheckMenuItem toggled itself; a failed switch
+                    // still needs the rebuild to restore the checkmarks.
+                    if switch_instance(app.clone(), id.to_string()).is_err() {
+                        refresh_tray_menu(app);
+                    }
+                }
+            }
         })
         .on_tray_icon_event(|tray, event| {
             if let TrayIconEvent::Click {
@@ -866,22 +1405,23 @@ fn forward_file_drop(
     }
 }
 
-fn get_server_url_from_store(app: &tauri::AppHandle) -> Option<String> {
-    app.store("config.json")
-        .ok()
-        .and_then(|store| store.get("server_url").and_then(|v| v.as_str().map(String::from)))
-}
-
-fn create_main_window(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
-    let url = get_server_url_from_store(app.handle())
-        .unwrap_or_else(|| DEFAULT_SERVER_URL.to_string());
-
-    let webview_url = WebviewUrl::External(url.parse()?);
+fn create_main_window(app: &tauri::AppHandle, visible: bool) -> Result<(), Box<dyn std::error::Error>> {
+    // Opens the last used instance
+    let instance = instances::active(app);
+    let url = take_start_url(&instance)?;
+    // A server the admin policy doesn't allow (any more) is never loaded;
+    // the settings page lets the user pick an allowed one.
+    #[cfg(desktop)]
+    let url = match policy::check_url(&instance.url) {
+        Ok(()) => url,
+        Err(e) => {
+            eprintln!("not opening {}: {e}", instance.url);
+            frontend_url("/?settings")
+        }
+    };
 
-    let server_host = url
-        .parse::<tauri::Url>()
-        .ok()
-        .and_then(|u| u.host_str().map(String::from));
+    let server_host = url.host_str().map(String::from);
+    let webview_url = WebviewUrl::External(url);
 
     let builder = WebviewWindowBuilder::new(app, "main", webview_url)
         .initialization_script(NOTIFICATION_BRIDGE_JS);
@@ -890,17 +1430,33 @@ fn create_main_window(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>
     let builder = builder.initialization_script(EXTERNAL_LINK_JS);
 
     #[cfg(mobile)]
-    let builder = builder.initialization_script(MOBILE_SETTINGS_BUTTON_JS);
+    let builder = builder
+        .initialization_script(WEBSOCKET_NOTIFICATION_JS)
+        .initialization_script(MOBILE_SETTINGS_BUTTON_JS);
 
-    #[cfg(target_os = "android")]
     let builder = builder.initialization_script(ACTIVE_ROOM_TRACKER_JS);
 
+    // Per-instance webview data store, so cookies, localStorage and service
+    // workers don't leak between instances (or accounts on the same host).
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    let builder = match instances::webview_data_root(app) {
+        Some(root) if !instance.default_data_store => builder.data_directory(root.join(&instance.id)),
+        _ => builder,
+    };
+    #[cfg(target_os = "macos")]
+    let builder = if instance.default_data_store {
+        builder
+    } else {
+        builder.data_store_identifier(instances::data_store_identifier(&instance.id))
+    };
+
     #[cfg(desktop)]
     let builder = {
         let server_host_clone = server_host.clone();
-        let app_handle = app.handle().clone();
+        let app_handle = app.clone();
         builder
-            .title("Chatto")
+            .title(PRODUCT_NAME)
+            .visible(visible)
             .inner_size(1024.0, 768.0)
             .min_inner_size(400.0, 300.0)
             .zoom_hotkeys_enabled(true)
@@ -921,10 +1477,10 @@ fn create_main_window(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>
 
     // Restore persisted zoom level
     #[cfg(desktop)]
-    if let Ok(store) = app.handle().store("config.json") {
-        if let Some(level) = store.get("zoom_level").and_then(|v| v.as_i64()) {
-            let level = (level as i32).clamp(30, 300);
-            ZOOM_LEVEL.store(level, Ordering::SeqCst);
+    {
+        let level = settings::get(app).zoom_level.clamp(30, 300);
+        ZOOM_LEVEL.store(level, Ordering::SeqCst);
+        if level != 100 {
             let _ = window.set_zoom(level as f64 / 100.0);
         }
     }
@@ -934,64 +1490,219 @@ fn create_main_window(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    #[cfg(desktop)]
+    cli::init();
+
     let builder = tauri::Builder::default();
 
     #[cfg(desktop)]
     let builder = builder.invoke_handler(tauri::generate_handler![
         set_server_url,
         get_server_url,
+        get_branding,
         clear_server_url,
         open_settings,
         open_external_url,
         show_notification,
+        set_active_room,
         get_notifications_enabled,
         set_notifications_enabled,
+        get_quiet_hours,
+        set_quiet_hours,
+        #[cfg(feature = "autostart")]
         get_autostart_enabled,
+        #[cfg(feature = "autostart")]
         set_autostart_enabled,
+        get_build_features,
+        get_start_minimized,
+        set_start_minimized,
+        get_close_behavior,
+        set_close_behavior,
+        #[cfg(feature = "updater")]
+        get_update_channel,
+        #[cfg(feature = "updater")]
+        set_update_channel,
+        #[cfg(feature = "updater")]
+        get_update_schedule,
+        #[cfg(feature = "updater")]
+        set_update_schedule,
+        #[cfg(feature = "updater")]
+        get_update_endpoints,
+        #[cfg(feature = "updater")]
+        set_update_endpoints,
+        #[cfg(feature = "updater")]
+        get_update_info,
+        #[cfg(feature = "updater")]
+        install_update,
+        #[cfg(feature = "updater")]
+        close_update_window,
+        get_tray_available,
+        get_policy_locks,
+        export_settings,
+        import_settings,
         check_instance_flow,
+        set_unread_count,
+        get_reply_context,
+        send_reply,
+        cancel_reply,
+        report_notification_failure,
+        get_instances,
+        get_active_instance,
+        add_instance,
+        remove_instance,
+        switch_instance,
     ]);
     #[cfg(mobile)]
     let builder = builder.invoke_handler(tauri::generate_handler![
         set_server_url,
         get_server_url,
+        get_branding,
         open_settings,
         show_notification,
+        set_active_room,
         get_notifications_enabled,
         set_notifications_enabled,
+        get_quiet_hours,
+        set_quiet_hours,
+        get_instances,
+        get_active_instance,
+        add_instance,
+        remove_instance,
+        switch_instance,
     ]);
 
+    // A second launch (app launcher, autostart, xdg-open chatto://…) hands
+    // its arguments to the running process and exits. Must be registered
+    // first. With the deep-link feature, chatto:// URLs in the forwarded
+    // arguments arrive through deep_link().on_open_url like any other link.
+    //
+    // Each --profile is a separate process with its own settings, so only the
+    // default profile takes part.
+    #[cfg(desktop)]
+    let builder = if profile().is_none() {
+        builder.plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            let args = match cli::parse(argv.into_iter().skip(1)) {
+                Ok(cli::Command::Run(args)) => args,
+                _ => cli::Args::default(),
+            };
+            let server_url = args.server_url.as_ref().filter(|url| match policy::check_url(url) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("ignoring --server-url: {e}");
+                    false
+                }
+            });
+            if let Some(url) = server_url {
+                instances::set_override(app, url);
+                refresh_tray_menu(app);
+                let _ = open_active_instance(app);
+            }
+            if !args.hidden {
+                show_main_window(app);
+            }
+        }))
+    } else {
+        builder
+    };
+
+    // File pickers for settings export/import
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_dialog::init());
+
     let builder = builder
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_notification::init())
         .setup(|app| {
-            // Autostart
+            // Restore a damaged config.json from backup before the store
+            // loads it
+            backups::recover(app.handle());
+
+            // Command-line overrides
+            #[cfg(desktop)]
+            {
+                let args = cli::args();
+                if args.reset_settings {
+                    settings::reset(app.handle())?;
+                }
+            }
+
+            // Bring config.json up to the current schema before anything
+            // reads it
+            if let Err(e) = settings::migrate(app.handle()) {
+                eprintln!("failed to migrate settings: {e}");
+            }
+
+            // A server pinned by the admin policy replaces the stored one
             #[cfg(desktop)]
+            if let Some(pinned) = &policy::get().server_url {
+                if instances::active(app.handle()).url != *pinned {
+                    if let Err(e) = instances::set_active_url(app.handle(), pinned) {
+                        eprintln!("failed to apply the policy's server_url: {e}");
+                    }
+                }
+            }
+
+            #[cfg(desktop)]
+            if let Some(url) = &cli::args().server_url {
+                match policy::check_url(url) {
+                    Ok(()) => instances::set_override(app.handle(), url),
+                    Err(e) => eprintln!("ignoring --server-url: {e}"),
+                }
+            }
+
+            // Autostart
+            #[cfg(all(desktop, feature = "autostart"))]
             {
-                use tauri_plugin_autostart::MacosLauncher;
+                use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
+                // The login item passes --autostart so the launch can honour
+                // the "start minimized" setting.
                 app.handle().plugin(tauri_plugin_autostart::init(
                     MacosLauncher::LaunchAgent,
-                    None,
+                    Some(vec!["--autostart"]),
                 ))?;
+                app.add_capability(include_str!("../optional-capabilities/autostart.json"))?;
+                // Re-register existing login items so ones created by older
+                // versions pick up the flag.
+                let autolaunch = app.autolaunch();
+                if autolaunch.is_enabled().unwrap_or(false) {
+                    let _ = autolaunch.enable();
+                }
             }
 
             // Window state persistence
+            // Visibility isn't restored: whether the window starts shown is up
+            // to --hidden, not to how the last session ended.
             #[cfg(desktop)]
-            app.handle()
-                .plugin(tauri_plugin_window_state::Builder::default().build())?;
+            app.handle().plugin(
+                tauri_plugin_window_state::Builder::default()
+                    .with_state_flags(
+                        tauri_plugin_window_state::StateFlags::all()
+                            - tauri_plugin_window_state::StateFlags::VISIBLE,
+                    )
+                    .build(),
+            )?;
 
             // Auto-updater
-            #[cfg(desktop)]
-            app.handle()
-                .plugin(tauri_plugin_updater::Builder::new().build())?;
+            #[cfg(all(desktop, feature = "updater"))]
+            {
+                app.handle()
+                    .plugin(tauri_plugin_updater::Builder::new().build())?;
+                app.add_capability(include_str!("../optional-capabilities/updater.json"))?;
+            }
 
             // Deep links
             #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
             app.deep_link().register_all()?;
 
+            // Links received at launch are routed before the main window
+            // exists, so it opens straight on the linked page.
             if let Some(urls) = app.deep_link().get_current()? {
                 eprintln!("launched via deep link: {:?}", urls);
+                if let Some(url) = urls.first() {
+                    deep_link::handle(app.handle(), url);
+                }
             }
 
             let app_handle = app.handle().clone();
@@ -999,13 +1710,7 @@ pub fn run() {
                 let urls = event.urls();
                 eprintln!("deep link opened: {:?}", urls);
                 if let Some(url) = urls.first() {
-                    if url.scheme() != "chatto" {
-                        eprintln!("rejected deep link with unexpected scheme: {}", url.scheme());
-                        return;
-                    }
-                    if let Some(window) = app_handle.get_webview_window("main") {
-                        let _ = window.navigate(url.clone());
-                    }
+                    deep_link::handle(&app_handle, url);
                 }
             });
 
@@ -1014,19 +1719,53 @@ pub fn run() {
             setup_app_menu(app)?;
 
             // System tray
-            #[cfg(desktop)]
-            setup_tray(app)?;
+            #[cfg(all(desktop, feature = "tray"))]
+            {
+                tray_host::watch(app.handle().clone());
+                setup_tray(app)?;
+            }
 
-            // Create main window
-            create_main_window(app)?;
+            // Drop webview profiles of removed instances
+            #[cfg(any(target_os = "linux", target_os = "windows"))]
+            if let Err(e) = instances::prune_webview_data(app.handle()) {
+                eprintln!("failed to prune webview data: {e}");
+            }
 
-            // Background update check on startup
+            // Create main window
             #[cfg(desktop)]
-            {
-                let update_handle = app.handle().clone();
-                tauri::async_runtime::spawn(do_update_check(update_handle, true));
+            let visible = {
+                let args = cli::args();
+                let start_minimized = args.autostart
+                    && get_start_minimized(app.handle().clone()).unwrap_or(false);
+                !(args.hidden || start_minimized)
+            };
+            #[cfg(mobile)]
+            let visible = true;
+            // Without a tray a hidden window couldn't be reached; start
+            // minimized to the taskbar instead.
+            #[cfg(desktop)]
+            let minimize = !visible && !tray_host::available();
+            #[cfg(desktop)]
+            let visible = visible || minimize;
+            create_main_window(app.handle(), visible)?;
+            #[cfg(desktop)]
+            if minimize {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.minimize();
+                }
             }
 
+            // Summary of what quiet hours held back, once they're over
+            quiet_hours::watch(app.handle().clone());
+
+            // Notification subscription to the active instance
+            #[cfg(desktop)]
+            subscription::start(app.handle().clone());
+
+            // Background update checks, at startup and then periodically
+            #[cfg(all(desktop, feature = "updater"))]
+            updates::schedule(app.handle().clone());
+
             Ok(())
         });
 
@@ -1034,11 +1773,45 @@ pub fn run() {
     #[cfg(desktop)]
     let builder = builder.on_window_event(|window, event| {
         match event {
-            tauri::WindowEvent::CloseRequested { api, .. } => {
-                let _ = window.hide();
-                api.prevent_close();
+            tauri::WindowEvent::CloseRequested { api, .. } if window.label() == "main" => {
+                use settings::CloseBehavior;
+                match settings::get(window.app_handle()).close_behavior {
+                    CloseBehavior::Quit => window.app_handle().exit(0),
+                    CloseBehavior::Minimize => {
+                        let _ = window.minimize();
+                        api.prevent_close();
+                    }
+                    CloseBehavior::Tray if !tray_host::available() => {
+                        let _ = window.minimize();
+                        api.prevent_close();
+                        #[cfg(feature = "tray")]
+                        explain_missing_tray(window.app_handle());
+                    }
+                    CloseBehavior::Tray => {
+                        let _ = window.hide();
+                        api.prevent_close();
+                    }
+                }
+            }
+            tauri::WindowEvent::Destroyed => {
+                if window.label() == "main" && REOPEN_MAIN_WINDOW.load(Ordering::SeqCst) {
+                    // Defer to the next event loop turn so the old "main"
+                    // label is released before the new window claims it. The
+                    // flag stays set until then so the exit request that
+                    // follows the last window closing is held off.
+                    let app = window.app_handle().clone();
+                    let _ = window.app_handle().run_on_main_thread(move || {
+                        if let Err(e) = create_main_window(&app, true) {
+                            eprintln!("failed to reopen main window: {e}");
+                        }
+                        REOPEN_MAIN_WINDOW.store(false, Ordering::SeqCst);
+                    });
+                }
             }
             tauri::WindowEvent::Focused(focused) => {
+                if *focused && window.label() == "main" {
+                    badge::clear(window.app_handle());
+                }
                 let js = if *focused {
                     "window.__chattoWindowHidden=false;document.dispatchEvent(new Event('visibilitychange'));"
                 } else {
@@ -1071,6 +1844,20 @@ pub fn run() {
                     }
                 }
             }
+            // The main window is briefly gone while switching instances,
+            // which must not quit the app. Any other last window going away
+            // still exits, as do explicit exits (app.exit), which carry a code.
+            #[cfg(desktop)]
+            if let tauri::RunEvent::ExitRequested { code: None, api, .. } = &event {
+                if REOPEN_MAIN_WINDOW.load(Ordering::SeqCst) {
+                    api.prevent_exit();
+                }
+            }
+            // "Install on next quit"
+            #[cfg(all(desktop, feature = "updater"))]
+            if let tauri::RunEvent::Exit = &event {
+                updates::install_staged();
+            }
             let _ = (app, event);
         });
 }
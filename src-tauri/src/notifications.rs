@@ -0,0 +1,577 @@
+// Native notifications that know which room they came from.
+//
+// tauri-plugin-notification has no click callbacks on desktop, so message
+// notifications with a room attached go straight to the platform backend
+// (org.freedesktop.Notifications over D-Bus, WinRT toasts,
+// NSUserNotification) and wait for activation. On Linux a single listener
+// handles the actions of every notification. Everything else, and all of
+// mobile, still goes through the plugin.
+//
+// Where the notification server supports actions, message notifications also
+// offer "Reply" and "Mark as read", each only when the instance's GraphQL
+// schema has the mutation behind it (checked by introspection when the
+// subscription connects). Both run as same-origin fetches inside the main
+// webview, so they carry the session cookies and never raise the window.
+// macOS has an inline reply field; elsewhere "Reply" opens a small compose
+// window. macOS offers no "Mark as read": its only other button is the one
+// that dismisses the notification.
+//
+// The main webview reports the room it shows (ACTIVE_ROOM_TRACKER_JS), so
+// while the window has focus only that room's notifications are dropped.
+
+#[cfg(desktop)]
+use serde_json::json;
+use std::sync::Mutex;
+use tauri::Manager;
+
+// Mutations behind the notification actions, and the field and arguments
+// each needs from the schema. $s/$r are spaceId and roomId as in the bridge's
+// roomEvents query.
+#[cfg(desktop)]
+const POST_MESSAGE_MUTATION: &str =
+    "mutation($s:ID!,$r:ID!,$b:String!){postMessage(spaceId:$s,roomId:$r,body:$b){id}}";
+#[cfg(desktop)]
+const POST_MESSAGE_FIELD: (&str, &[&str]) = ("postMessage", &["spaceId", "roomId", "body"]);
+#[cfg(all(desktop, not(target_os = "macos")))]
+const MARK_READ_MUTATION: &str = "mutation($s:ID!,$r:ID!){markRoomAsRead(spaceId:$s,roomId:$r)}";
+#[cfg(desktop)]
+const MARK_READ_FIELD: (&str, &[&str]) = ("markRoomAsRead", &["spaceId", "roomId"]);
+#[cfg(desktop)]
+const REPLY_FAILED: &str = "Couldn't send reply";
+
+// Actions each instance's schema supports, by server URL.
+#[cfg(desktop)]
+static SUPPORTED: Mutex<Vec<(String, Actions)>> = Mutex::new(Vec::new());
+
+// Room open in the main window, from its /chat/<space>/<room> route.
+static ACTIVE_ROOM: Mutex<Option<String>> = Mutex::new(None);
+
+// Notification the reply window is composing an answer to.
+#[cfg(desktop)]
+static REPLY_TARGET: Mutex<Option<(RoomTarget, ReplyContext)>> = Mutex::new(None);
+
+/// Shown at the top of the reply window.
+#[cfg(desktop)]
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ReplyContext {
+    pub title: String,
+    pub body: String,
+}
+
+/// What the user picked on a message notification.
+#[cfg(desktop)]
+enum Action {
+    Open,
+    Reply,
+    /// Text typed into the notification's own reply field (macOS)
+    #[cfg(target_os = "macos")]
+    ReplyWith(String),
+    #[cfg(not(target_os = "macos"))]
+    MarkRead,
+}
+
+/// The actions a message notification can offer for an instance.
+#[cfg(desktop)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Actions {
+    pub reply: bool,
+    pub mark_read: bool,
+}
+
+#[cfg(desktop)]
+impl Actions {
+    /// From the server's mutations as (name, argument names); see
+    /// subscription::fetch_mutations.
+    pub fn from_mutations(mutations: &[(String, Vec<String>)]) -> Self {
+        let has = |(name, args): (&str, &[&str])| {
+            mutations.iter().any(|(field, field_args)| {
+                field == name && args.iter().all(|arg| field_args.iter().any(|a| a == arg))
+            })
+        };
+        Self {
+            reply: has(POST_MESSAGE_FIELD),
+            mark_read: has(MARK_READ_FIELD),
+        }
+    }
+}
+
+#[cfg(desktop)]
+pub fn set_supported_actions(server_url: &str, actions: Actions) {
+    if let Ok(mut supported) = SUPPORTED.lock() {
+        supported.retain(|(url, _)| url != server_url);
+        supported.push((server_url.to_string(), actions));
+    }
+}
+
+/// Actions for notifications from `server_url`; none until its schema has
+/// been checked.
+#[cfg(desktop)]
+fn supported_actions(server_url: &str) -> Actions {
+    SUPPORTED
+        .lock()
+        .ok()
+        .and_then(|supported| {
+            supported
+                .iter()
+                .find(|(url, _)| url == server_url)
+                .map(|(_, actions)| *actions)
+        })
+        .unwrap_or_default()
+}
+
+/// The room a message notification points at. `space_id` is "DM" for direct
+/// messages, matching the web app's /chat/<space>/<room> routes.
+#[derive(Clone, Debug)]
+pub struct RoomTarget {
+    pub server_url: String,
+    pub space_id: String,
+    pub room_id: String,
+}
+
+impl RoomTarget {
+    pub fn url(&self) -> Option<tauri::Url> {
+        let mut url: tauri::Url = self.server_url.parse().ok()?;
+        url.path_segments_mut()
+            .ok()?
+            .pop_if_empty()
+            .extend(["chat", self.space_id.as_str(), self.room_id.as_str()]);
+        Some(url)
+    }
+}
+
+/// Bring the main window to the front and navigate it to the target room,
+/// switching to the room's instance the way chatto:// links do.
+#[cfg(desktop)]
+pub fn open_room(app: &tauri::AppHandle, target: &RoomTarget) {
+    let result = target
+        .url()
+        .ok_or_else(|| format!("Invalid server URL {}", target.server_url))
+        .and_then(|url| crate::deep_link::open_url(app, url));
+    if let Err(e) = result {
+        eprintln!("failed to open room {}: {e}", target.room_id);
+    }
+}
+
+#[cfg(desktop)]
+fn handle_action(app: &tauri::AppHandle, target: &RoomTarget, context: ReplyContext, action: Action) {
+    match action {
+        Action::Open => open_room(app, target),
+        Action::Reply => {
+            if let Ok(mut pending) = REPLY_TARGET.lock() {
+                *pending = Some((target.clone(), context));
+            }
+            if let Err(e) = open_reply_window(app) {
+                eprintln!("failed to open reply window: {e}");
+            }
+        }
+        #[cfg(target_os = "macos")]
+        Action::ReplyWith(body) => {
+            if let Err(e) = post_reply(app, target, &body) {
+                report_reply_failure(app, &e, &body);
+            }
+        }
+        #[cfg(not(target_os = "macos"))]
+        Action::MarkRead => {
+            let variables = json!({ "s": target.space_id, "r": target.room_id });
+            if let Err(e) = run_in_main_webview(app, target, MARK_READ_MUTATION, variables, None) {
+                eprintln!("failed to mark room {} as read: {e}", target.room_id);
+            }
+        }
+    }
+}
+
+#[cfg(desktop)]
+pub fn report_failure(app: &tauri::AppHandle, title: &str, reason: &str) {
+    use tauri_plugin_notification::NotificationExt;
+    let _ = app.notification().builder().title(title).body(reason).show();
+}
+
+/// Tell the user a reply didn't go out, with what they typed so it can be
+/// copied and sent again. Only for the inline reply field; the reply window
+/// keeps the text and shows the error itself.
+#[cfg(target_os = "macos")]
+fn report_reply_failure(app: &tauri::AppHandle, reason: &str, body: &str) {
+    eprintln!("failed to send reply: {reason}");
+    report_failure(app, REPLY_FAILED, &format!("{reason}\n\n{}", body.trim()));
+}
+
+/// Run a GraphQL request as a fetch inside the main webview, which is
+/// same-origin with the instance and holds its session cookies. When the
+/// response carries errors and `failure` is set, a notification with its
+/// title reports them, followed by its text (the reply that wasn't sent).
+#[cfg(desktop)]
+fn run_in_main_webview(
+    app: &tauri::AppHandle,
+    target: &RoomTarget,
+    query: &str,
+    variables: serde_json::Value,
+    failure: Option<(&str, &str)>,
+) -> Result<(), String> {
+    let window = app.get_webview_window("main").ok_or("no main window")?;
+    let current = window.url().map_err(|e| e.to_string())?;
+    let same_origin = target
+        .server_url
+        .parse::<tauri::Url>()
+        .map(|u| u.origin() == current.origin())
+        .unwrap_or(false);
+    if !same_origin {
+        return Err(format!(
+            "{} is not showing this instance right now",
+            crate::PRODUCT_NAME
+        ));
+    }
+
+    let payload = json!({ "query": query, "variables": variables });
+    let on_error = match failure {
+        Some((title, text)) => format!(
+            "if(window.__TAURI_INTERNALS__)window.__TAURI_INTERNALS__.invoke('report_notification_failure',{{title:{},reason:e+'\\n\\n'+{}}}).catch(function(){{}});",
+            json!(title),
+            json!(text)
+        ),
+        None => String::new(),
+    };
+    let script = format!(
+        r#"(function(){{
+            function fail(e){{{on_error}}}
+            fetch('/api/graphql',{{method:'POST',headers:{{'Content-Type':'application/json'}},body:JSON.stringify({payload})}})
+                .then(function(r){{return r.json();}})
+                .then(function(d){{if(d&&d.errors&&d.errors.length)fail(d.errors[0].message||'Request failed');}})
+                .catch(function(e){{fail(String(e));}});
+        }})();"#
+    );
+    window.eval(&script).map_err(|e| e.to_string())
+}
+
+#[cfg(desktop)]
+fn post_reply(app: &tauri::AppHandle, target: &RoomTarget, body: &str) -> Result<(), String> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Ok(());
+    }
+    let variables = json!({ "s": target.space_id, "r": target.room_id, "b": body });
+    run_in_main_webview(
+        app,
+        target,
+        POST_MESSAGE_MUTATION,
+        variables,
+        Some((REPLY_FAILED, body)),
+    )
+}
+
+#[cfg(desktop)]
+fn open_reply_window(app: &tauri::AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window("reply") {
+        // Already open for an earlier notification: reload to pick up the
+        // new target and bring it forward.
+        let _ = window.navigate(crate::frontend_url("/?reply"));
+        let _ = window.show();
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+    tauri::WebviewWindowBuilder::new(
+        app,
+        "reply",
+        tauri::WebviewUrl::External(crate::frontend_url("/?reply")),
+    )
+    .title("Reply")
+    .inner_size(420.0, 260.0)
+    .resizable(false)
+    .always_on_top(true)
+    .build()
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+/// The notification the reply window answers, if any.
+#[cfg(desktop)]
+pub fn reply_context() -> Option<ReplyContext> {
+    REPLY_TARGET
+        .lock()
+        .ok()
+        .and_then(|pending| pending.as_ref().map(|(_, context)| context.clone()))
+}
+
+/// Send the reply window's text to its room and close the window. On failure
+/// the window stays open with the text, so the user can try again.
+#[cfg(desktop)]
+pub fn send_reply(app: &tauri::AppHandle, body: &str) -> Result<(), String> {
+    let target = REPLY_TARGET
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_ref()
+        .map(|(target, _)| target.clone())
+        .ok_or("Nothing to reply to")?;
+    post_reply(app, &target, body)?;
+    close_reply_window(app);
+    Ok(())
+}
+
+#[cfg(desktop)]
+pub fn close_reply_window(app: &tauri::AppHandle) {
+    if let Ok(mut pending) = REPLY_TARGET.lock() {
+        *pending = None;
+    }
+    if let Some(window) = app.get_webview_window("reply") {
+        let _ = window.close();
+    }
+}
+
+/// Record the room the main window shows; None off a room page.
+pub fn set_active_room(room_id: Option<String>) {
+    if let Ok(mut active) = ACTIVE_ROOM.lock() {
+        *active = room_id.filter(|id| !id.is_empty());
+    }
+}
+
+/// Whether the user is looking at `room_id` right now: it is open in the main
+/// window and the window has focus.
+pub fn on_screen(app: &tauri::AppHandle, room_id: &str) -> bool {
+    let focused = app
+        .get_webview_window("main")
+        .and_then(|w| w.is_focused().ok())
+        .unwrap_or(false);
+    focused
+        && ACTIVE_ROOM
+            .lock()
+            .is_ok_and(|active| active.as_deref() == Some(room_id))
+}
+
+pub fn show(
+    app: &tauri::AppHandle,
+    title: &str,
+    body: &str,
+    target: Option<RoomTarget>,
+) -> Result<(), String> {
+    #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+    if let Some(target) = target {
+        return show_native(app, title, body, target);
+    }
+    #[cfg(mobile)]
+    let _ = target;
+
+    use tauri_plugin_notification::NotificationExt;
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| e.to_string())
+}
+
+// Message notifications still on screen, by the id the notification server
+// gave them, oldest first.
+#[cfg(target_os = "linux")]
+static SHOWN: Mutex<Vec<(u32, RoomTarget, ReplyContext)>> = Mutex::new(Vec::new());
+// Servers that keep notifications around may never report them closed; past
+// this many the oldest stop reacting to actions.
+#[cfg(target_os = "linux")]
+const MAX_SHOWN: usize = 100;
+// Session bus connection that sends message notifications and receives the
+// actions on all of them.
+#[cfg(target_os = "linux")]
+static BUS: Mutex<Option<zbus::blocking::Connection>> = Mutex::new(None);
+#[cfg(target_os = "linux")]
+const FREEDESKTOP_NOTIFICATIONS: &str = "org.freedesktop.Notifications";
+
+#[cfg(target_os = "linux")]
+fn take_shown(id: u32) -> Option<(RoomTarget, ReplyContext)> {
+    let mut shown = SHOWN.lock().ok()?;
+    let index = shown.iter().position(|(shown_id, _, _)| *shown_id == id)?;
+    let (_, target, context) = shown.remove(index);
+    Some((target, context))
+}
+
+/// The notification connection, connecting and starting the action listener
+/// on first use.
+#[cfg(target_os = "linux")]
+fn notification_bus(app: &tauri::AppHandle) -> Result<zbus::blocking::Connection, String> {
+    let mut bus = BUS.lock().map_err(|e| e.to_string())?;
+    if let Some(connection) = bus.as_ref() {
+        return Ok(connection.clone());
+    }
+    let connection = zbus::blocking::Connection::session().map_err(|e| e.to_string())?;
+    let proxy = zbus::blocking::Proxy::new(
+        &connection,
+        FREEDESKTOP_NOTIFICATIONS,
+        "/org/freedesktop/Notifications",
+        FREEDESKTOP_NOTIFICATIONS,
+    )
+    .map_err(|e| e.to_string())?;
+    let invoked = proxy
+        .receive_signal("ActionInvoked")
+        .map_err(|e| e.to_string())?;
+    let closed = proxy
+        .receive_signal("NotificationClosed")
+        .map_err(|e| e.to_string())?;
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for signal in invoked {
+            let Ok((id, action)) = signal.body().deserialize::<(u32, String)>() else {
+                continue;
+            };
+            let action = match action.as_str() {
+                // What freedesktop servers invoke on a plain click
+                "default" => Action::Open,
+                "reply" => Action::Reply,
+                "mark_read" => Action::MarkRead,
+                _ => continue,
+            };
+            if let Some((target, context)) = take_shown(id) {
+                handle_action(&app, &target, context, action);
+            }
+        }
+    });
+    std::thread::spawn(move || {
+        for signal in closed {
+            if let Ok((id, _reason)) = signal.body().deserialize::<(u32, u32)>() {
+                take_shown(id);
+            }
+        }
+    });
+
+    *bus = Some(connection.clone());
+    Ok(connection)
+}
+
+#[cfg(target_os = "linux")]
+fn show_native(
+    app: &tauri::AppHandle,
+    title: &str,
+    body: &str,
+    target: RoomTarget,
+) -> Result<(), String> {
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    let connection = notification_bus(app)?;
+    let supported = supported_actions(&target.server_url);
+    // Sent as a flat list of key, label, key, label, …
+    let actions: Vec<&str> = [
+        Some(("default", "Open")),
+        Some(("reply", "Reply")).filter(|_| supported.reply),
+        Some(("mark_read", "Mark as read")).filter(|_| supported.mark_read),
+    ]
+    .into_iter()
+    .flatten()
+    .flat_map(|(key, label)| [key, label])
+    .collect();
+    let hints: HashMap<&str, Value> = HashMap::new();
+    let reply = connection
+        .call_method(
+            Some(FREEDESKTOP_NOTIFICATIONS),
+            "/org/freedesktop/Notifications",
+            Some(FREEDESKTOP_NOTIFICATIONS),
+            "Notify",
+            &(
+                app.package_info().name.as_str(),
+                0u32,
+                "",
+                title,
+                body,
+                actions,
+                hints,
+                -1i32,
+            ),
+        )
+        .map_err(|e| e.to_string())?;
+    let id: u32 = reply.body().deserialize().map_err(|e| e.to_string())?;
+
+    let context = ReplyContext {
+        title: title.to_string(),
+        body: body.to_string(),
+    };
+    let mut shown = SHOWN.lock().map_err(|e| e.to_string())?;
+    if shown.len() >= MAX_SHOWN {
+        shown.remove(0);
+    }
+    shown.push((id, target, context));
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn show_native(
+    app: &tauri::AppHandle,
+    title: &str,
+    body: &str,
+    target: RoomTarget,
+) -> Result<(), String> {
+    use tauri_winrt_notification::Toast;
+
+    // Unpackaged dev builds have no registered AppUserModelID; borrow
+    // PowerShell's like tauri-plugin-notification does.
+    let app_id = if tauri::is_dev() {
+        Toast::POWERSHELL_APP_ID.to_string()
+    } else {
+        app.config().identifier.clone()
+    };
+
+    let supported = supported_actions(&target.server_url);
+    let mut toast = Toast::new(&app_id).title(title).text1(body);
+    if supported.reply {
+        toast = toast.add_button("Reply", "reply");
+    }
+    if supported.mark_read {
+        toast = toast.add_button("Mark as read", "mark_read");
+    }
+
+    let app = app.clone();
+    let context = ReplyContext {
+        title: title.to_string(),
+        body: body.to_string(),
+    };
+    toast
+        // Called with the button's argument, or None for a click on the toast
+        .on_activated(move |arg| {
+            let action = match arg.as_deref() {
+                Some("reply") => Action::Reply,
+                Some("mark_read") => Action::MarkRead,
+                _ => Action::Open,
+            };
+            handle_action(&app, &target, context.clone(), action);
+            Ok(())
+        })
+        .show()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn show_native(
+    app: &tauri::AppHandle,
+    title: &str,
+    body: &str,
+    target: RoomTarget,
+) -> Result<(), String> {
+    use mac_notification_sys::{MainButton, Notification, NotificationResponse};
+
+    // Only the first call takes effect; later ones error and are ignored.
+    let _ = mac_notification_sys::set_application(if tauri::is_dev() {
+        "com.apple.Terminal"
+    } else {
+        &app.config().identifier
+    });
+
+    let reply = supported_actions(&target.server_url).reply;
+    let app = app.clone();
+    let context = ReplyContext {
+        title: title.to_string(),
+        body: body.to_string(),
+    };
+    // send() blocks until the notification is acted on or dismissed
+    std::thread::spawn(move || {
+        let mut notification = Notification::new();
+        notification
+            .title(&context.title)
+            .message(&context.body)
+            .wait_for_click(true);
+        if reply {
+            notification.main_button(MainButton::Response("Reply"));
+        }
+        let action = match notification.send() {
+            Ok(NotificationResponse::Click) => Action::Open,
+            Ok(NotificationResponse::Reply(text)) => Action::ReplyWith(text),
+            _ => return,
+        };
+        handle_action(&app, &target, context, action);
+    });
+    Ok(())
+}
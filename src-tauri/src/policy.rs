@@ -0,0 +1,201 @@
+// Admin policy for managed deployments.
+//
+// A JSON file installed by IT, read once at startup:
+//
+//   Linux    /etc/chatto-desktop/policy.json
+//   macOS    /Library/Application Support/Chatto/policy.json
+//   Windows  %ProgramData%\Chatto\policy.json
+//
+//   {
+//     "server_url": "https://chat.example.org",
+//     "allowed_hosts": ["chat.example.org", "*.chat.example.org"],
+//     "disable_update_check": true,
+//     "update_endpoints": ["https://artifacts.example.org/chatto/latest.json"],
+//     "notifications_enabled": true,
+//     "hide_autostart": true
+//   }
+//
+// Every key is optional. Locked values win over config.json and can't be
+// changed from the app; the settings page shows them read-only.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// The only server Chatto may use.
+    pub server_url: Option<String>,
+    /// Hosts servers may be on. "*.example.org" matches its subdomains.
+    pub allowed_hosts: Option<Vec<String>>,
+    pub disable_update_check: bool,
+    /// Update manifests to use instead of the built-in release server.
+    pub update_endpoints: Option<Vec<String>>,
+    /// Force notifications on or off.
+    pub notifications_enabled: Option<bool>,
+    /// Hide the start-at-login toggle and leave the login item as installed.
+    pub hide_autostart: bool,
+}
+
+static POLICY: OnceLock<Policy> = OnceLock::new();
+
+#[cfg(target_os = "linux")]
+fn path() -> Option<PathBuf> {
+    Some("/etc/chatto-desktop/policy.json".into())
+}
+
+#[cfg(target_os = "macos")]
+fn path() -> Option<PathBuf> {
+    Some("/Library/Application Support/Chatto/policy.json".into())
+}
+
+#[cfg(target_os = "windows")]
+fn path() -> Option<PathBuf> {
+    std::env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join("Chatto").join("policy.json"))
+}
+
+/// The policy in effect; empty when no policy file is installed.
+pub fn get() -> &'static Policy {
+    POLICY.get_or_init(|| {
+        let Some(path) = path() else {
+            return Policy::default();
+        };
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return Policy::default();
+        };
+        match serde_json::from_str(&contents) {
+            Ok(policy) => {
+                eprintln!("using admin policy from {}", path.display());
+                policy
+            }
+            Err(e) => {
+                eprintln!("ignoring invalid admin policy {}: {e}", path.display());
+                Policy::default()
+            }
+        }
+    })
+}
+
+fn host_allowed(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.ends_with('.')),
+        None => host == pattern,
+    }
+}
+
+/// Whether the policy lets Chatto connect to `url`.
+pub fn check_url(url: &str) -> Result<(), String> {
+    check_url_against(get(), url)
+}
+
+fn check_url_against(policy: &Policy, url: &str) -> Result<(), String> {
+    let parsed: tauri::Url = url.parse().map_err(|e| format!("Invalid URL: {e}"))?;
+    let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+
+    if let Some(pinned) = &policy.server_url {
+        let same_origin = pinned
+            .parse::<tauri::Url>()
+            .is_ok_and(|p| p.origin() == parsed.origin());
+        if !same_origin {
+            return Err(format!(
+                "Your administrator has set {} to use {pinned} only",
+                crate::PRODUCT_NAME
+            ));
+        }
+    }
+    if let Some(allowed) = &policy.allowed_hosts {
+        if !allowed.iter().any(|pattern| host_allowed(pattern, &host)) {
+            return Err(format!(
+                "Your administrator does not allow connecting to {host}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// What the settings page shows as managed.
+#[derive(Clone, Debug, Serialize)]
+pub struct Locks {
+    pub server_url: bool,
+    pub notifications: bool,
+    pub autostart_hidden: bool,
+    pub updates_disabled: bool,
+    pub update_endpoints: bool,
+}
+
+pub fn locks() -> Locks {
+    let policy = get();
+    Locks {
+        server_url: policy.server_url.is_some(),
+        notifications: policy.notifications_enabled.is_some(),
+        autostart_hidden: policy.hide_autostart,
+        updates_disabled: policy.disable_update_check,
+        update_endpoints: policy.update_endpoints.is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowing(hosts: &[&str]) -> Policy {
+        Policy {
+            allowed_hosts: Some(hosts.iter().map(|h| h.to_string()).collect()),
+            ..Policy::default()
+        }
+    }
+
+    fn pinned(url: &str) -> Policy {
+        Policy {
+            server_url: Some(url.into()),
+            ..Policy::default()
+        }
+    }
+
+    #[test]
+    fn exact_host_pattern() {
+        assert!(host_allowed("chat.example.org", "chat.example.org"));
+        assert!(!host_allowed("chat.example.org", "eu.chat.example.org"));
+        assert!(!host_allowed("chat.example.org", "example.org"));
+    }
+
+    #[test]
+    fn wildcard_pattern_matches_subdomains_only() {
+        assert!(host_allowed("*.example.org", "chat.example.org"));
+        assert!(host_allowed("*.example.org", "eu.chat.example.org"));
+        assert!(!host_allowed("*.example.org", "example.org"));
+        assert!(!host_allowed("*.example.org", "badexample.org"));
+    }
+
+    #[test]
+    fn host_case_is_ignored() {
+        let policy = allowing(&[" Chat.Example.ORG "]);
+        assert!(check_url_against(&policy, "https://CHAT.example.org/chat").is_ok());
+        assert!(
+            check_url_against(&allowing(&["*.Example.org"]), "https://Chat.EXAMPLE.org").is_ok()
+        );
+        assert!(check_url_against(&policy, "https://other.example.org").is_err());
+    }
+
+    #[test]
+    fn pinned_server_url_compares_origins() {
+        let policy = pinned("https://chat.example.org/");
+        assert!(check_url_against(&policy, "https://chat.example.org").is_ok());
+        assert!(check_url_against(&policy, "https://chat.example.org/chat/s1/r1").is_ok());
+        assert!(check_url_against(&policy, "https://CHAT.example.org:443").is_ok());
+        assert!(check_url_against(&policy, "http://chat.example.org").is_err());
+        assert!(check_url_against(&policy, "https://chat.example.org:8443").is_err());
+        assert!(check_url_against(&policy, "https://eu.chat.example.org").is_err());
+    }
+
+    #[test]
+    fn no_policy_allows_everything() {
+        assert!(check_url_against(&Policy::default(), "https://anything.example.com").is_ok());
+        assert!(check_url_against(&Policy::default(), "not a url").is_err());
+    }
+}
@@ -0,0 +1,365 @@
+// Quiet hours: a weekly schedule during which notifications are held back.
+//
+// Stored in config.json as `quiet_hours`, in local time:
+//
+//   {
+//     "enabled": true,
+//     "ranges": [
+//       { "days": ["mon", "tue", "wed", "thu", "fri", "sun"], "start": "19:00", "end": "08:00" },
+//       { "days": ["sat", "sun"], "start": "00:00", "end": "00:00" }
+//     ],
+//     "allow_direct_messages": true,
+//     "allow_mentions": true
+//   }
+//
+// A range starts on each of its days and runs into the next day when `end`
+// isn't after `start`; equal times cover the whole day. Direct messages and
+// mentions can be let through. What was held back is summed up in one
+// notification once quiet hours are over.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{Datelike, Local, Timelike};
+use serde::{Deserialize, Serialize};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const MINUTES_PER_WEEK: u32 = 7 * MINUTES_PER_DAY;
+// How long a mention event marks its room. The message notification it
+// belongs to follows within moments.
+const MENTION_WINDOW: Duration = Duration::from_secs(10);
+// How often the watcher looks for the end of quiet hours.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+// Notifications held back since quiet hours began, counted per title (the
+// sender), so a busy night doesn't pile up one entry per message.
+static HELD: Mutex<Vec<(String, u32)>> = Mutex::new(Vec::new());
+// Rooms that just had a mention, with when.
+static MENTIONS: Mutex<Vec<(String, Instant)>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietRange {
+    pub days: Vec<Day>,
+    /// "HH:MM"
+    pub start: String,
+    /// "HH:MM"; on the next day when not after `start`.
+    pub end: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub ranges: Vec<QuietRange>,
+    pub allow_direct_messages: bool,
+    pub allow_mentions: bool,
+}
+
+impl Default for QuietHours {
+    /// Off, with weekday nights and weekends ready to switch on.
+    fn default() -> Self {
+        use Day::*;
+        Self {
+            enabled: false,
+            ranges: vec![
+                // Sunday too, so the night before Monday is quiet
+                QuietRange {
+                    days: vec![Mon, Tue, Wed, Thu, Fri, Sun],
+                    start: "19:00".into(),
+                    end: "08:00".into(),
+                },
+                QuietRange {
+                    days: vec![Sat, Sun],
+                    start: "00:00".into(),
+                    end: "00:00".into(),
+                },
+            ],
+            allow_direct_messages: true,
+            allow_mentions: true,
+        }
+    }
+}
+
+/// Minutes since midnight for "HH:MM".
+fn minutes(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+impl QuietRange {
+    /// Whether the range covers `minute_of_week` (0 = Monday 00:00).
+    fn covers(&self, minute_of_week: u32) -> bool {
+        let (Some(start), Some(end)) = (minutes(&self.start), minutes(&self.end)) else {
+            return false;
+        };
+        let length = if end > start {
+            end - start
+        } else {
+            end + MINUTES_PER_DAY - start
+        };
+        self.days.iter().any(|day| {
+            let from = *day as u32 * MINUTES_PER_DAY + start;
+            // Modulo the week, so Sunday night runs into Monday morning
+            (minute_of_week + MINUTES_PER_WEEK - from) % MINUTES_PER_WEEK < length
+        })
+    }
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> Result<(), String> {
+        for range in &self.ranges {
+            if range.days.is_empty() {
+                return Err("Each quiet hours range needs at least one day".into());
+            }
+            for time in [&range.start, &range.end] {
+                if minutes(time).is_none() {
+                    return Err(format!("Invalid time \"{time}\", expected HH:MM"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn active_at(&self, minute_of_week: u32) -> bool {
+        self.enabled && self.ranges.iter().any(|range| range.covers(minute_of_week))
+    }
+
+    /// Whether a direct message or mention gets through anyway.
+    fn lets_through(&self, direct: bool, mention: bool) -> bool {
+        (direct && self.allow_direct_messages) || (mention && self.allow_mentions)
+    }
+
+    pub fn active_now(&self) -> bool {
+        let now = Local::now();
+        let minute_of_week =
+            now.weekday().num_days_from_monday() * MINUTES_PER_DAY + now.hour() * 60 + now.minute();
+        self.active_at(minute_of_week)
+    }
+}
+
+fn load(app: &tauri::AppHandle) -> QuietHours {
+    crate::settings::load(app)
+        .map(|s| s.quiet_hours)
+        .unwrap_or_default()
+}
+
+/// Remember that `room_id` just had a mention, so the message notification
+/// that goes with it counts as one.
+pub fn note_mention(room_id: &str) {
+    if let Ok(mut mentions) = MENTIONS.lock() {
+        mentions.retain(|(_, at)| at.elapsed() < MENTION_WINDOW);
+        mentions.push((room_id.to_string(), Instant::now()));
+    }
+}
+
+pub fn mentioned(room_id: &str) -> bool {
+    MENTIONS.lock().is_ok_and(|mentions| {
+        mentions
+            .iter()
+            .any(|(room, at)| room == room_id && at.elapsed() < MENTION_WINDOW)
+    })
+}
+
+/// Whether a notification has to wait for the end of quiet hours. Held ones
+/// are remembered for the summary.
+pub fn hold(app: &tauri::AppHandle, title: &str, direct: bool, mention: bool) -> bool {
+    let quiet = load(app);
+    if !quiet.active_now() || quiet.lets_through(direct, mention) {
+        return false;
+    }
+    if let Ok(mut held) = HELD.lock() {
+        count(&mut held, title);
+    }
+    true
+}
+
+/// Add one held notification from `sender`.
+fn count(held: &mut Vec<(String, u32)>, sender: &str) {
+    match held.iter_mut().find(|(name, _)| name == sender) {
+        Some((_, count)) => *count += 1,
+        None => held.push((sender.to_string(), 1)),
+    }
+}
+
+/// "5 notifications from Alice, Bob and 2 others"
+fn summary(held: &[(String, u32)]) -> String {
+    let senders: Vec<&str> = held.iter().map(|(sender, _)| sender.as_str()).collect();
+    let from = match senders.as_slice() {
+        [one] => one.to_string(),
+        [first, second] => format!("{first} and {second}"),
+        [first, second, third] => format!("{first}, {second} and {third}"),
+        [first, second, rest @ ..] => format!("{first}, {second} and {} others", rest.len()),
+        [] => String::new(),
+    };
+    match held.iter().map(|(_, count)| count).sum::<u32>() {
+        1 => format!("1 notification from {from}"),
+        n => format!("{n} notifications from {from}"),
+    }
+}
+
+/// Show the summary of held-back notifications once quiet hours are over.
+pub fn watch(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        if load(&app).active_now() {
+            continue;
+        }
+        let held = match HELD.lock() {
+            Ok(mut held) => std::mem::take(&mut *held),
+            Err(_) => continue,
+        };
+        if held.is_empty() || !crate::get_notifications_enabled(app.clone()).unwrap_or(false) {
+            continue;
+        }
+        if let Err(e) =
+            crate::notifications::show(&app, "Quiet hours are over", &summary(&held), None)
+        {
+            eprintln!("failed to show quiet hours summary: {e}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Minute of the week for `day` at "HH:MM"
+    fn at(day: Day, time: &str) -> u32 {
+        day as u32 * MINUTES_PER_DAY + minutes(time).unwrap()
+    }
+
+    fn quiet(days: Vec<Day>, start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            enabled: true,
+            ranges: vec![QuietRange {
+                days,
+                start: start.into(),
+                end: end.into(),
+            }],
+            ..QuietHours::default()
+        }
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(minutes("00:00"), Some(0));
+        assert_eq!(minutes("08:30"), Some(510));
+        assert_eq!(minutes("23:59"), Some(1439));
+        assert_eq!(minutes("24:00"), None);
+        assert_eq!(minutes("12:60"), None);
+        assert_eq!(minutes("7pm"), None);
+        assert_eq!(minutes(""), None);
+    }
+
+    #[test]
+    fn range_within_a_day() {
+        let quiet = quiet(vec![Day::Wed], "12:00", "13:00");
+        assert!(!quiet.active_at(at(Day::Wed, "11:59")));
+        assert!(quiet.active_at(at(Day::Wed, "12:00")));
+        assert!(quiet.active_at(at(Day::Wed, "12:59")));
+        assert!(!quiet.active_at(at(Day::Wed, "13:00")));
+        assert!(!quiet.active_at(at(Day::Thu, "12:30")));
+    }
+
+    #[test]
+    fn range_wraps_past_midnight() {
+        let quiet = quiet(vec![Day::Tue], "22:00", "06:00");
+        assert!(quiet.active_at(at(Day::Tue, "23:00")));
+        assert!(quiet.active_at(at(Day::Wed, "05:59")));
+        assert!(!quiet.active_at(at(Day::Wed, "06:00")));
+        // Starts on its days only
+        assert!(!quiet.active_at(at(Day::Tue, "03:00")));
+    }
+
+    #[test]
+    fn range_wraps_past_the_end_of_the_week() {
+        let quiet = quiet(vec![Day::Sun], "23:00", "08:00");
+        assert!(!quiet.active_at(at(Day::Sun, "22:59")));
+        assert!(quiet.active_at(at(Day::Sun, "23:00")));
+        assert!(quiet.active_at(at(Day::Mon, "00:00")));
+        assert!(quiet.active_at(at(Day::Mon, "07:59")));
+        assert!(!quiet.active_at(at(Day::Mon, "08:00")));
+    }
+
+    #[test]
+    fn equal_times_cover_the_whole_day() {
+        let quiet = quiet(vec![Day::Sat], "00:00", "00:00");
+        assert!(!quiet.active_at(at(Day::Fri, "23:59")));
+        assert!(quiet.active_at(at(Day::Sat, "00:00")));
+        assert!(quiet.active_at(at(Day::Sat, "23:59")));
+        assert!(!quiet.active_at(at(Day::Sun, "00:00")));
+    }
+
+    #[test]
+    fn disabled_is_never_active() {
+        let mut quiet = quiet(vec![Day::Sat], "00:00", "00:00");
+        quiet.enabled = false;
+        assert!(!quiet.active_at(at(Day::Sat, "12:00")));
+    }
+
+    #[test]
+    fn validate_rejects_bad_ranges() {
+        assert!(QuietHours::default().validate().is_ok());
+        assert!(quiet(vec![Day::Mon], "24:00", "08:00").validate().is_err());
+        assert!(quiet(vec![Day::Mon], "19:00", "8am").validate().is_err());
+        assert!(quiet(vec![], "19:00", "08:00").validate().is_err());
+    }
+
+    #[test]
+    fn direct_messages_and_mentions_get_through_when_allowed() {
+        let mut quiet = QuietHours::default();
+        assert!(quiet.lets_through(true, false));
+        assert!(quiet.lets_through(false, true));
+        assert!(!quiet.lets_through(false, false));
+
+        quiet.allow_direct_messages = false;
+        quiet.allow_mentions = false;
+        assert!(!quiet.lets_through(true, true));
+    }
+
+    #[test]
+    fn counts_per_sender() {
+        let mut held = Vec::new();
+        count(&mut held, "Alice");
+        count(&mut held, "Bob");
+        count(&mut held, "Alice");
+        assert_eq!(held, vec![("Alice".to_string(), 2), ("Bob".to_string(), 1)]);
+    }
+
+    #[test]
+    fn summarizes_senders() {
+        let held = |senders: &[&str]| -> Vec<(String, u32)> {
+            senders.iter().map(|s| (s.to_string(), 1)).collect()
+        };
+        assert_eq!(summary(&held(&["Alice"])), "1 notification from Alice");
+        assert_eq!(
+            summary(&[("Alice".to_string(), 3)]),
+            "3 notifications from Alice"
+        );
+        assert_eq!(
+            summary(&held(&["Alice", "Bob"])),
+            "2 notifications from Alice and Bob"
+        );
+        assert_eq!(
+            summary(&held(&["Alice", "Bob", "Carol"])),
+            "3 notifications from Alice, Bob and Carol"
+        );
+        assert_eq!(
+            summary(&held(&["Alice", "Bob", "Carol", "Dave", "Erin"])),
+            "5 notifications from Alice, Bob and 3 others"
+        );
+    }
+}
@@ -0,0 +1,518 @@
+// Typed view of config.json.
+//
+// Every setting lives under its own top-level key, as before, so stores stay
+// readable by older builds and by the Android NotificationService. Missing
+// keys take the defaults below, and a key holding the wrong type is ignored
+// rather than failing the whole load.
+//
+// "schema_version" records which migrations a store has been through. Stores
+// written before it existed count as version 0. migrate() runs once at
+// startup and brings them up to SCHEMA_VERSION; to change how something is
+// stored, append a step to MIGRATIONS instead of patching values on read.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::Mutex;
+use tauri_plugin_store::StoreExt;
+
+use crate::instances::{self, Instance};
+use crate::quiet_hours::QuietHours;
+
+// Upgrades a store from schema version i to i + 1.
+const MIGRATIONS: &[fn(&mut Settings)] = &[seed_instances];
+
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+// Serializes load-modify-save cycles so concurrent updates don't drop each
+// other's changes.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// What closing the main window does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloseBehavior {
+    #[default]
+    Tray,
+    Minimize,
+    Quit,
+}
+
+/// Which releases the updater offers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub schema_version: u32,
+    pub instances: Vec<Instance>,
+    pub active_instance: Option<String>,
+    /// Mirror of the active instance's URL, None for the default server. Kept
+    /// for stores read by builds without multi-instance support.
+    pub server_url: Option<String>,
+    pub notifications_enabled: bool,
+    /// Percent, 100 = unzoomed.
+    pub zoom_level: i32,
+    pub start_minimized: bool,
+    pub close_behavior: CloseBehavior,
+    /// The one-time "no system tray" explanation was shown.
+    pub tray_fallback_explained: bool,
+    pub update_channel: UpdateChannel,
+    /// Re-check for updates in the background while Chatto runs.
+    pub auto_update_check: bool,
+    pub update_check_interval_hours: u32,
+    /// Newest version a background check already announced.
+    pub announced_update: Option<String>,
+    /// Update manifests to use instead of the built-in release server, e.g.
+    /// an internal mirror. Empty for the default.
+    pub update_endpoints: Vec<String>,
+    pub quiet_hours: QuietHours,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            schema_version: 0,
+            instances: Vec::new(),
+            active_instance: None,
+            server_url: None,
+            notifications_enabled: true,
+            zoom_level: 100,
+            start_minimized: false,
+            close_behavior: CloseBehavior::default(),
+            tray_fallback_explained: false,
+            update_channel: UpdateChannel::default(),
+            auto_update_check: true,
+            update_check_interval_hours: 24,
+            announced_update: None,
+            update_endpoints: Vec::new(),
+            quiet_hours: QuietHours::default(),
+        }
+    }
+}
+
+/// Build settings from the store's entries, skipping keys whose value doesn't
+/// fit the field.
+fn from_entries(entries: Vec<(String, Value)>) -> Settings {
+    let all: Map<String, Value> = entries.iter().cloned().collect();
+    if let Ok(settings) = serde_json::from_value(Value::Object(all)) {
+        return settings;
+    }
+
+    let mut accepted = Map::new();
+    for (key, value) in entries {
+        let mut candidate = accepted.clone();
+        candidate.insert(key.clone(), value.clone());
+        if serde_json::from_value::<Settings>(Value::Object(candidate)).is_ok() {
+            accepted.insert(key, value);
+        } else {
+            eprintln!("ignoring invalid setting \"{key}\": {value}");
+        }
+    }
+    serde_json::from_value(Value::Object(accepted)).unwrap_or_default()
+}
+
+pub fn load(app: &tauri::AppHandle) -> Result<Settings, String> {
+    let store = app.store(crate::config_file()).map_err(|e| e.to_string())?;
+    Ok(from_entries(store.entries()))
+}
+
+/// Settings, or the defaults when the store can't be opened.
+#[cfg(desktop)]
+pub fn get(app: &tauri::AppHandle) -> Settings {
+    load(app).unwrap_or_else(|e| {
+        eprintln!("failed to read settings: {e}");
+        Settings::default()
+    })
+}
+
+fn write(app: &tauri::AppHandle, settings: &Settings) -> Result<(), String> {
+    let store = app.store(crate::config_file()).map_err(|e| e.to_string())?;
+    let Value::Object(values) = serde_json::to_value(settings).map_err(|e| e.to_string())? else {
+        return Err("settings did not serialize to an object".into());
+    };
+    for (key, value) in values {
+        if value.is_null() {
+            store.delete(&key);
+        } else {
+            store.set(key, value);
+        }
+    }
+    store.save().map_err(|e| e.to_string())?;
+    crate::backups::rotate(app);
+    Ok(())
+}
+
+/// Change settings and save them.
+pub fn update<T>(
+    app: &tauri::AppHandle,
+    change: impl FnOnce(&mut Settings) -> T,
+) -> Result<T, String> {
+    let _guard = WRITE_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut settings = load(app)?;
+    let result = change(&mut settings);
+    write(app, &settings)?;
+    Ok(result)
+}
+
+/// Drop every stored setting (--reset-settings).
+#[cfg(desktop)]
+pub fn reset(app: &tauri::AppHandle) -> Result<(), String> {
+    let _guard = WRITE_LOCK.lock().map_err(|e| e.to_string())?;
+    let store = app.store(crate::config_file()).map_err(|e| e.to_string())?;
+    store.clear();
+    store.save().map_err(|e| e.to_string())?;
+    crate::backups::rotate(app);
+    Ok(())
+}
+
+/// Bring the store up to SCHEMA_VERSION. A store from a newer build is left
+/// alone; the fields this build knows still load with their defaults.
+pub fn migrate(app: &tauri::AppHandle) -> Result<(), String> {
+    let _guard = WRITE_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut settings = load(app)?;
+    if settings.schema_version > SCHEMA_VERSION {
+        eprintln!(
+            "config.json has schema version {}, newer than this build's {SCHEMA_VERSION}",
+            settings.schema_version
+        );
+        return Ok(());
+    }
+    if settings.schema_version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let from = settings.schema_version;
+    run_migrations(&mut settings);
+    eprintln!("migrated config.json from schema version {from} to {SCHEMA_VERSION}");
+    write(app, &settings)
+}
+
+/// Apply the migrations `settings` hasn't been through yet. Settings from a
+/// newer schema must be caught before this.
+fn run_migrations(settings: &mut Settings) {
+    for step in &MIGRATIONS[settings.schema_version as usize..] {
+        step(settings);
+    }
+    settings.schema_version = SCHEMA_VERSION;
+}
+
+/// 0 → 1: multi-instance support. Seed the instance list from server_url (or
+/// the default server) so the first instance's id stays stable from now on.
+fn seed_instances(settings: &mut Settings) {
+    if !settings.instances.is_empty() {
+        return;
+    }
+    let url = settings
+        .server_url
+        .clone()
+        .unwrap_or_else(|| crate::DEFAULT_SERVER_URL.to_string());
+    let instance = Instance {
+        id: instances::new_id(),
+        label: instances::label_for(&url),
+        url,
+        // Profiles never share the default store with the main profile
+        default_data_store: crate::profile().is_none(),
+    };
+    settings.active_instance = Some(instance.id.clone());
+    settings.instances = vec![instance];
+}
+
+/// Reject update manifest URLs that aren't https. Both the settings page and
+/// imported files go through this.
+#[cfg(desktop)]
+pub fn check_update_endpoint(url: &str) -> Result<(), String> {
+    let parsed: tauri::Url = url.parse().map_err(|e| format!("Invalid URL {url}: {e}"))?;
+    if parsed.scheme() != "https" {
+        return Err(format!("{url} must use https://"));
+    }
+    Ok(())
+}
+
+// Settings files written by export_settings. EXPORT_VERSION covers the file's
+// envelope; the settings inside carry their own schema_version and are
+// migrated on import like a stored config.json.
+#[cfg(desktop)]
+const EXPORT_FORMAT: &str = "chatto-desktop-settings";
+#[cfg(desktop)]
+const EXPORT_VERSION: u32 = 1;
+
+#[cfg(desktop)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Export {
+    pub format: String,
+    pub version: u32,
+    pub settings: Settings,
+    /// Start at login. Lives with the OS, not in config.json.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autostart: Option<bool>,
+}
+
+#[cfg(desktop)]
+impl Export {
+    pub fn new(mut settings: Settings, autostart: Option<bool>) -> Self {
+        // Per-machine state, not a preference
+        settings.tray_fallback_explained = false;
+        Self {
+            format: EXPORT_FORMAT.into(),
+            version: EXPORT_VERSION,
+            settings,
+            autostart,
+        }
+    }
+
+    /// Parse and check an exported file. Unlike config.json, values of the
+    /// wrong type are errors here rather than being skipped.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let export: Export = serde_json::from_str(contents)
+            .map_err(|e| format!("Not a {} settings file: {e}", crate::PRODUCT_NAME))?;
+        if export.format != EXPORT_FORMAT {
+            return Err(format!("Not a {} settings file", crate::PRODUCT_NAME));
+        }
+        if export.version > EXPORT_VERSION {
+            return Err(format!(
+                "This settings file was exported by a newer version of {}",
+                crate::PRODUCT_NAME
+            ));
+        }
+
+        let settings = &export.settings;
+        if settings.schema_version > SCHEMA_VERSION {
+            return Err(format!(
+                "This settings file was exported by a newer version of {}",
+                crate::PRODUCT_NAME
+            ));
+        }
+        if !(30..=300).contains(&settings.zoom_level) {
+            return Err(format!("Invalid zoom level {}", settings.zoom_level));
+        }
+        if settings.update_check_interval_hours == 0 {
+            return Err("The update check interval must be at least an hour".into());
+        }
+        let urls = settings
+            .instances
+            .iter()
+            .map(|i| &i.url)
+            .chain(settings.server_url.as_ref());
+        for url in urls {
+            let valid = url
+                .parse::<tauri::Url>()
+                .is_ok_and(|u| matches!(u.scheme(), "http" | "https") && u.host_str().is_some());
+            if !valid {
+                return Err(format!("Invalid server URL \"{url}\""));
+            }
+        }
+        for url in &settings.update_endpoints {
+            check_update_endpoint(url)?;
+        }
+        settings.quiet_hours.validate()?;
+        for (n, instance) in settings.instances.iter().enumerate() {
+            if instance.id.is_empty() || settings.instances[..n].iter().any(|i| i.id == instance.id)
+            {
+                return Err(format!(
+                    "Invalid or duplicate instance id \"{}\"",
+                    instance.id
+                ));
+            }
+        }
+        if let Some(active) = &settings.active_instance {
+            if !settings.instances.iter().any(|i| &i.id == active) {
+                return Err("The active instance is not in the instance list".into());
+            }
+        }
+        Ok(export)
+    }
+}
+
+/// Replace the stored settings with imported ones, migrating them to the
+/// current schema first.
+#[cfg(desktop)]
+pub fn import(app: &tauri::AppHandle, mut imported: Settings) -> Result<(), String> {
+    let _guard = WRITE_LOCK.lock().map_err(|e| e.to_string())?;
+    let current = load(app)?;
+
+    run_migrations(&mut imported);
+    if imported.instances.is_empty() {
+        return Err("The settings file has no instances".into());
+    }
+    imported.tray_fallback_explained = current.tray_fallback_explained;
+    write(app, &imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entries(store: Value) -> Vec<(String, Value)> {
+        let Value::Object(map) = store else {
+            panic!("store must be an object");
+        };
+        map.into_iter().collect()
+    }
+
+    #[test]
+    fn v0_store_keeps_legacy_server_url() {
+        let mut settings = from_entries(entries(json!({
+            "server_url": "https://chat.example.org",
+            "notifications_enabled": false,
+        })));
+        assert_eq!(settings.schema_version, 0);
+
+        run_migrations(&mut settings);
+        assert_eq!(settings.schema_version, SCHEMA_VERSION);
+        assert_eq!(settings.instances.len(), 1);
+        let instance = &settings.instances[0];
+        assert_eq!(instance.url, "https://chat.example.org");
+        assert_eq!(instance.label, "chat.example.org");
+        // Keeps the session the pre-multi-instance build logged in with
+        assert!(instance.default_data_store);
+        assert_eq!(settings.active_instance.as_ref(), Some(&instance.id));
+        assert!(!settings.notifications_enabled);
+    }
+
+    #[test]
+    fn v0_store_without_server_url_gets_default_server() {
+        let mut settings = from_entries(Vec::new());
+        run_migrations(&mut settings);
+        assert_eq!(settings.instances.len(), 1);
+        assert_eq!(settings.instances[0].url, crate::DEFAULT_SERVER_URL);
+    }
+
+    #[test]
+    fn migrations_keep_existing_instances() {
+        let mut settings = from_entries(entries(json!({
+            "instances": [{ "id": "a", "label": "A", "url": "https://a.example.org" }],
+            "active_instance": "a",
+            "server_url": "https://legacy.example.org",
+        })));
+        run_migrations(&mut settings);
+        assert_eq!(settings.instances.len(), 1);
+        assert_eq!(settings.instances[0].id, "a");
+    }
+
+    #[test]
+    fn invalid_values_fall_back_to_defaults() {
+        let settings = from_entries(entries(json!({
+            "zoom_level": "large",
+            "close_behavior": "explode",
+            "start_minimized": true,
+            "some_future_key": [1, 2, 3],
+        })));
+        assert_eq!(settings.zoom_level, 100);
+        assert_eq!(settings.close_behavior, CloseBehavior::Tray);
+        assert!(settings.start_minimized);
+    }
+
+    #[test]
+    fn future_schema_version_loads_as_is() {
+        let settings = from_entries(entries(json!({
+            "schema_version": SCHEMA_VERSION + 1,
+            "zoom_level": 120,
+        })));
+        assert_eq!(settings.schema_version, SCHEMA_VERSION + 1);
+        assert_eq!(settings.zoom_level, 120);
+    }
+
+    /// An exported file with one instance, as JSON to tamper with.
+    #[cfg(desktop)]
+    fn export() -> Value {
+        let mut settings = Settings::default();
+        seed_instances(&mut settings);
+        settings.schema_version = SCHEMA_VERSION;
+        serde_json::to_value(Export::new(settings, Some(true))).unwrap()
+    }
+
+    #[cfg(desktop)]
+    fn parse(export: &Value) -> Result<Export, String> {
+        Export::parse(&export.to_string())
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn export_round_trips() {
+        let parsed = parse(&export()).unwrap();
+        assert_eq!(parsed.autostart, Some(true));
+        assert_eq!(parsed.settings.instances.len(), 1);
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn export_rejects_other_files() {
+        let mut file = export();
+        file["format"] = json!("something-else");
+        assert!(parse(&file).is_err());
+        assert!(Export::parse("{}").is_err());
+        assert!(Export::parse("not json").is_err());
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn export_rejects_newer_versions() {
+        let mut file = export();
+        file["version"] = json!(EXPORT_VERSION + 1);
+        assert!(parse(&file).unwrap_err().contains("newer version"));
+
+        let mut file = export();
+        file["settings"]["schema_version"] = json!(SCHEMA_VERSION + 1);
+        assert!(parse(&file).unwrap_err().contains("newer version"));
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn export_rejects_bad_instance_ids() {
+        let mut file = export();
+        let instance = file["settings"]["instances"][0].clone();
+        file["settings"]["instances"] = json!([instance.clone(), instance.clone()]);
+        assert!(parse(&file).unwrap_err().contains("duplicate"));
+
+        let mut file = export();
+        file["settings"]["instances"][0]["id"] = json!("");
+        file["settings"]["active_instance"] = Value::Null;
+        assert!(parse(&file).is_err());
+
+        let mut file = export();
+        file["settings"]["active_instance"] = json!("missing");
+        assert!(parse(&file).is_err());
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn export_rejects_bad_values() {
+        let mut file = export();
+        file["settings"]["instances"][0]["url"] = json!("ftp://chat.example.org");
+        assert!(parse(&file).unwrap_err().contains("server URL"));
+
+        let mut file = export();
+        file["settings"]["zoom_level"] = json!(1000);
+        assert!(parse(&file).is_err());
+
+        let mut file = export();
+        file["settings"]["update_check_interval_hours"] = json!(0);
+        assert!(parse(&file).unwrap_err().contains("interval"));
+
+        // Wrong types fail here rather than being skipped like in config.json
+        let mut file = export();
+        file["settings"]["zoom_level"] = json!("large");
+        assert!(parse(&file).is_err());
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn export_rejects_insecure_update_endpoints() {
+        let mut file = export();
+        file["settings"]["update_endpoints"] = json!(["https://updates.example.org/latest.json"]);
+        assert!(parse(&file).is_ok());
+
+        file["settings"]["update_endpoints"] = json!(["http://updates.example.org/latest.json"]);
+        assert!(parse(&file).unwrap_err().contains("https://"));
+
+        file["settings"]["update_endpoints"] = json!(["not a url"]);
+        assert!(parse(&file).is_err());
+    }
+}
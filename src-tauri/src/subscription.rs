@@ -0,0 +1,700 @@
+// Native graphql-transport-ws client for desktop notifications.
+//
+// Holds its own myInstanceEvents subscription to the active instance, the way
+// the Android NotificationService does, instead of sniffing the web app's
+// socket. It authenticates with the main webview's session cookies and feeds
+// show_notification directly, so notifications keep working while the page is
+// on the settings screen or failed to load.
+//
+// The protocol side (Endpoint, run_session, fetch_latest_message,
+// fetch_mutations) knows
+// nothing about Tauri and can be pointed at any http:// or https:// server,
+// e.g. a local mock GraphQL server.
+
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tauri::Manager;
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::HeaderValue;
+use tungstenite::Message as WsMessage;
+
+const SUBSCRIPTION_QUERY: &str =
+    "subscription{myInstanceEvents{event{__typename ...on NotificationCreatedEvent{spaceId roomId} ...on MentionNotificationEvent{room{id}}}}}";
+const ROOM_EVENTS_QUERY: &str =
+    "query($s:ID!,$r:ID!){roomEvents(spaceId:$s,roomId:$r,limit:1){actor{displayName}event{__typename...on MessagePostedEvent{body}}}}";
+
+// Introspection of the mutations, to learn which notification actions the
+// server can back.
+const MUTATIONS_QUERY: &str = "{__type(name:\"Mutation\"){fields{name args{name}}}}";
+
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+// Socket read timeout; bounds how long a session takes to notice should_stop.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Bumped whenever the active instance (or its URL) changes, so the running
+// session drops its connection and reconnects to the new one.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// HTTP and WebSocket addresses of an instance's /api/graphql endpoint.
+#[derive(Clone, Debug)]
+pub struct Endpoint {
+    pub http_url: tauri::Url,
+    pub ws_url: tauri::Url,
+}
+
+impl Endpoint {
+    pub fn for_server(server_url: &str) -> Result<Self, String> {
+        let base: tauri::Url = server_url.parse().map_err(|e| format!("Invalid URL: {e}"))?;
+        let http_url = base.join("/api/graphql").map_err(|e| e.to_string())?;
+        let mut ws_url = http_url.clone();
+        let scheme = if http_url.scheme() == "https" { "wss" } else { "ws" };
+        ws_url
+            .set_scheme(scheme)
+            .map_err(|_| format!("Cannot derive WebSocket URL from {server_url}"))?;
+        Ok(Self { http_url, ws_url })
+    }
+}
+
+/// A NotificationCreatedEvent from the subscription stream. `space_id` is
+/// "DM" for direct messages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoomNotification {
+    pub space_id: String,
+    pub room_id: String,
+}
+
+/// Latest message in a room, used as the notification text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatestMessage {
+    pub author: Option<String>,
+    pub body: String,
+}
+
+/// Run one subscription session: connect, subscribe to myInstanceEvents and
+/// report every NotificationCreatedEvent until the server closes the
+/// connection, an error occurs, or `should_stop` returns true. Returns
+/// Ok(()) only when stopped on request.
+pub fn run_session(
+    endpoint: &Endpoint,
+    cookie: &str,
+    should_stop: &dyn Fn() -> bool,
+    on_notification: &mut dyn FnMut(RoomNotification),
+) -> Result<(), String> {
+    let mut request = endpoint
+        .ws_url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| e.to_string())?;
+    let headers = request.headers_mut();
+    headers.insert(
+        "Sec-WebSocket-Protocol",
+        HeaderValue::from_static("graphql-transport-ws"),
+    );
+    headers.insert(
+        "Cookie",
+        HeaderValue::from_str(cookie).map_err(|e| e.to_string())?,
+    );
+
+    let host = endpoint.ws_url.host_str().ok_or("URL has no host")?;
+    let port = endpoint
+        .ws_url
+        .port_or_known_default()
+        .ok_or("URL has no port")?;
+    let stream = TcpStream::connect((host, port)).map_err(|e| e.to_string())?;
+    // The handshake runs blocking; the short read timeout is applied through
+    // a second handle to the same socket once it's done.
+    let timeout_handle = stream.try_clone().map_err(|e| e.to_string())?;
+    let (mut socket, _) = tungstenite::client_tls(request, stream).map_err(|e| e.to_string())?;
+    timeout_handle
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| e.to_string())?;
+
+    let send = |socket: &mut tungstenite::WebSocket<_>, msg: Value| {
+        socket
+            .send(WsMessage::Text(msg.to_string().into()))
+            .map_err(|e| e.to_string())
+    };
+
+    send(&mut socket, json!({ "type": "connection_init" }))?;
+    let mut last_ping = Instant::now();
+
+    loop {
+        if should_stop() {
+            let _ = socket.close(None);
+            return Ok(());
+        }
+        if last_ping.elapsed() >= KEEPALIVE_INTERVAL {
+            send(&mut socket, json!({ "type": "ping" }))?;
+            last_ping = Instant::now();
+        }
+
+        let frame = match socket.read() {
+            Ok(frame) => frame,
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                continue;
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+        let text = match &frame {
+            WsMessage::Text(_) => frame.to_text().map_err(|e| e.to_string())?,
+            WsMessage::Close(reason) => {
+                return Err(format!("server closed the connection: {reason:?}"));
+            }
+            _ => continue,
+        };
+        let Ok(msg) = serde_json::from_str::<Value>(text) else {
+            continue;
+        };
+
+        match msg["type"].as_str() {
+            Some("connection_ack") => {
+                send(
+                    &mut socket,
+                    json!({
+                        "id": "1",
+                        "type": "subscribe",
+                        "payload": { "query": SUBSCRIPTION_QUERY },
+                    }),
+                )?;
+            }
+            Some("ping") => {
+                send(&mut socket, json!({ "type": "pong" }))?;
+            }
+            Some("next") => {
+                let event = &msg["payload"]["data"]["myInstanceEvents"]["event"];
+                // MentionNotificationEvent fires alongside NotificationCreatedEvent
+                // and would only duplicate its notification; it just marks the
+                // room so quiet hours can let mentions through.
+                if event["__typename"] == "MentionNotificationEvent" {
+                    if let Some(room_id) = event["room"]["id"].as_str() {
+                        crate::quiet_hours::note_mention(room_id);
+                    }
+                    continue;
+                }
+                if event["__typename"] != "NotificationCreatedEvent" {
+                    continue;
+                }
+                let Some(room_id) = event["roomId"].as_str().filter(|r| !r.is_empty()) else {
+                    continue;
+                };
+                on_notification(RoomNotification {
+                    space_id: event["spaceId"].as_str().unwrap_or("DM").to_string(),
+                    room_id: room_id.to_string(),
+                });
+            }
+            Some("error") => {
+                return Err(format!("subscription error: {}", msg["payload"]));
+            }
+            Some("complete") => {
+                return Err("subscription completed by server".into());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Fetch the newest event of a room over HTTP. Returns None when it isn't a
+/// message (joins, leaves, …).
+pub fn fetch_latest_message(
+    endpoint: &Endpoint,
+    cookie: &str,
+    space_id: &str,
+    room_id: &str,
+) -> Result<Option<LatestMessage>, String> {
+    let payload = json!({
+        "query": ROOM_EVENTS_QUERY,
+        "variables": { "s": space_id, "r": room_id },
+    });
+    let data = post(endpoint, cookie, &payload)?;
+
+    let event = &data["data"]["roomEvents"][0];
+    let Some(body) = event["event"]["body"].as_str() else {
+        return Ok(None);
+    };
+    Ok(Some(LatestMessage {
+        author: event["actor"]["displayName"].as_str().map(String::from),
+        body: body.to_string(),
+    }))
+}
+
+/// The server's mutations as (name, argument names). Empty when the server
+/// doesn't allow introspection.
+pub fn fetch_mutations(
+    endpoint: &Endpoint,
+    cookie: &str,
+) -> Result<Vec<(String, Vec<String>)>, String> {
+    let data = post(endpoint, cookie, &json!({ "query": MUTATIONS_QUERY }))?;
+    let Some(fields) = data["data"]["__type"]["fields"].as_array() else {
+        return Ok(Vec::new());
+    };
+    Ok(fields
+        .iter()
+        .filter_map(|field| {
+            let name = field["name"].as_str()?.to_string();
+            let args = field["args"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|arg| arg["name"].as_str().map(String::from))
+                .collect();
+            Some((name, args))
+        })
+        .collect())
+}
+
+fn post(endpoint: &Endpoint, cookie: &str, payload: &Value) -> Result<Value, String> {
+    let response = ureq::post(endpoint.http_url.as_str())
+        .timeout(Duration::from_secs(10))
+        .set("Content-Type", "application/json")
+        .set("Cookie", cookie)
+        .send_string(&payload.to_string())
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&response).map_err(|e| e.to_string())
+}
+
+/// Cookie header for `url` from the main webview's cookie jar, or None when
+/// the user isn't logged in (yet).
+fn session_cookie(app: &tauri::AppHandle, url: &str) -> Option<String> {
+    let window = app.get_webview_window("main")?;
+    let cookies = window.cookies_for_url(url.parse().ok()?).ok()?;
+    let header = cookies
+        .iter()
+        .map(|c| format!("{}={}", c.name(), c.value()))
+        .collect::<Vec<_>>()
+        .join("; ");
+    Some(header).filter(|h| !h.is_empty())
+}
+
+/// Reconnect to the (possibly new) active instance.
+pub fn restart() {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Sleep for `delay`, waking early if the active instance changes.
+fn backoff(delay: Duration, generation: u64) {
+    let deadline = Instant::now() + delay;
+    while Instant::now() < deadline && GENERATION.load(Ordering::SeqCst) == generation {
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Keep a subscription to the active instance running for the lifetime of
+/// the app, reconnecting with exponential backoff like the Android service.
+pub fn start(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut attempt: u32 = 0;
+        let mut recent: Vec<(String, Instant)> = Vec::new();
+
+        loop {
+            let generation = GENERATION.load(Ordering::SeqCst);
+            let server_url = crate::instances::active(&app).url;
+            let delay = MAX_RECONNECT_DELAY.min(Duration::from_secs(1 << attempt.min(6)));
+
+            let Ok(endpoint) = Endpoint::for_server(&server_url) else {
+                backoff(MAX_RECONNECT_DELAY, generation);
+                continue;
+            };
+            let Some(cookie) = session_cookie(&app, &server_url) else {
+                // Not logged in yet, or the window is being rebuilt
+                attempt = attempt.saturating_add(1);
+                backoff(delay, generation);
+                continue;
+            };
+
+            // Offer only the notification actions this server has mutations
+            // for; a failed lookup keeps what was learned before
+            match fetch_mutations(&endpoint, &cookie) {
+                Ok(mutations) => crate::notifications::set_supported_actions(
+                    &server_url,
+                    crate::notifications::Actions::from_mutations(&mutations),
+                ),
+                Err(e) => eprintln!("failed to look up mutations of {server_url}: {e}"),
+            }
+
+            let connected_at = Instant::now();
+            let result = run_session(
+                &endpoint,
+                &cookie,
+                &|| GENERATION.load(Ordering::SeqCst) != generation,
+                &mut |notification| {
+                    // Drop repeats for the same room within 3s
+                    let now = Instant::now();
+                    recent.retain(|(_, at)| now.duration_since(*at) < Duration::from_secs(3));
+                    if recent.iter().any(|(room, _)| room == &notification.room_id) {
+                        return;
+                    }
+                    recent.push((notification.room_id.clone(), now));
+                    notify(&app, &endpoint, &cookie, notification);
+                },
+            );
+
+            match result {
+                Ok(()) => attempt = 0,
+                Err(e) => {
+                    eprintln!("notification subscription to {server_url}: {e}");
+                    // A session that stayed up for a while resets the backoff
+                    if connected_at.elapsed() > MAX_RECONNECT_DELAY {
+                        attempt = 0;
+                    }
+                    attempt = attempt.saturating_add(1);
+                    backoff(delay, generation);
+                }
+            }
+        }
+    });
+}
+
+fn notify(
+    app: &tauri::AppHandle,
+    endpoint: &Endpoint,
+    cookie: &str,
+    notification: RoomNotification,
+) {
+    // The user is already reading this room
+    if crate::notifications::on_screen(app, &notification.room_id) {
+        return;
+    }
+    // Focusing the window clears the badge, so only count what arrives in
+    // the background
+    let focused = app
+        .get_webview_window("main")
+        .and_then(|w| w.is_focused().ok())
+        .unwrap_or(false);
+    if !focused {
+        crate::badge::increment(app);
+    }
+
+    let app = app.clone();
+    let endpoint = endpoint.clone();
+    let cookie = cookie.to_string();
+    // Fetch off the socket thread so a slow HTTP request can't stall it
+    std::thread::spawn(move || {
+        let message = fetch_latest_message(
+            &endpoint,
+            &cookie,
+            &notification.space_id,
+            &notification.room_id,
+        );
+        let (title, body) = match message {
+            Ok(Some(message)) => (
+                message.author.unwrap_or_else(|| crate::PRODUCT_NAME.into()),
+                message.body,
+            ),
+            // Not a message event (e.g. join/leave)
+            Ok(None) => return,
+            Err(_) => (crate::PRODUCT_NAME.into(), "New message".into()),
+        };
+        let _ = crate::show_notification(
+            app,
+            title,
+            body,
+            Some(notification.space_id),
+            Some(notification.room_id),
+            None,
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use tungstenite::handshake::server::{Request, Response};
+    use tungstenite::WebSocket;
+
+    const COOKIE: &str = "session=abc123";
+
+    /// A listener on a free local port and the endpoint pointing at it.
+    fn mock_server() -> (TcpListener, Endpoint) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        (listener, Endpoint::for_server(&url).unwrap())
+    }
+
+    /// Accept one graphql-transport-ws connection. Returns the socket and the
+    /// Cookie header the client sent.
+    fn accept_ws(listener: &TcpListener) -> (WebSocket<TcpStream>, Option<String>) {
+        let (stream, _) = listener.accept().unwrap();
+        let mut cookie = None;
+        let socket =
+            tungstenite::accept_hdr(stream, |request: &Request, mut response: Response| {
+                cookie = request
+                    .headers()
+                    .get("Cookie")
+                    .and_then(|v| v.to_str().ok())
+                    .map(String::from);
+                assert_eq!(
+                    request.headers().get("Sec-WebSocket-Protocol").unwrap(),
+                    "graphql-transport-ws"
+                );
+                response.headers_mut().insert(
+                    "Sec-WebSocket-Protocol",
+                    HeaderValue::from_static("graphql-transport-ws"),
+                );
+                Ok(response)
+            })
+            .unwrap();
+        (socket, cookie)
+    }
+
+    fn read_json(socket: &mut WebSocket<TcpStream>) -> Value {
+        loop {
+            let frame = socket.read().unwrap();
+            if frame.is_text() {
+                return serde_json::from_str(frame.to_text().unwrap()).unwrap();
+            }
+        }
+    }
+
+    fn send_json(socket: &mut WebSocket<TcpStream>, msg: Value) {
+        socket
+            .send(WsMessage::Text(msg.to_string().into()))
+            .unwrap();
+    }
+
+    fn next_event(event: Value) -> Value {
+        json!({
+            "id": "1",
+            "type": "next",
+            "payload": { "data": { "myInstanceEvents": { "event": event } } },
+        })
+    }
+
+    /// Answer one HTTP request with `response`. Returns the request head and
+    /// its JSON body.
+    fn serve_http_once(
+        listener: TcpListener,
+        response: Value,
+    ) -> thread::JoinHandle<(String, Value)> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().unwrap())
+                })
+                .unwrap_or(0);
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let response = response.to_string();
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                response.len()
+            )
+            .unwrap();
+            (head, serde_json::from_slice(&body).unwrap())
+        })
+    }
+
+    #[test]
+    fn endpoint_for_server() {
+        let endpoint = Endpoint::for_server("https://chat.example.org/chat/DM/r1").unwrap();
+        assert_eq!(
+            endpoint.http_url.as_str(),
+            "https://chat.example.org/api/graphql"
+        );
+        assert_eq!(
+            endpoint.ws_url.as_str(),
+            "wss://chat.example.org/api/graphql"
+        );
+
+        let endpoint = Endpoint::for_server("http://127.0.0.1:8080").unwrap();
+        assert_eq!(endpoint.ws_url.as_str(), "ws://127.0.0.1:8080/api/graphql");
+    }
+
+    #[test]
+    fn session_subscribes_and_reports_notifications() {
+        let (listener, endpoint) = mock_server();
+        let server = thread::spawn(move || {
+            let (mut socket, cookie) = accept_ws(&listener);
+            assert_eq!(read_json(&mut socket), json!({ "type": "connection_init" }));
+            send_json(&mut socket, json!({ "type": "connection_ack" }));
+            let subscribe = read_json(&mut socket);
+
+            send_json(
+                &mut socket,
+                next_event(json!({
+                    "__typename": "MentionNotificationEvent",
+                    "room": { "id": "mentioned-room" },
+                })),
+            );
+            send_json(
+                &mut socket,
+                next_event(json!({
+                    "__typename": "NotificationCreatedEvent",
+                    "spaceId": "s1",
+                    "roomId": "mentioned-room",
+                })),
+            );
+            send_json(
+                &mut socket,
+                next_event(json!({ "__typename": "MessagePostedEvent", "body": "ignored" })),
+            );
+            send_json(
+                &mut socket,
+                next_event(json!({ "__typename": "NotificationCreatedEvent", "roomId": "r2" })),
+            );
+            // Until the client hangs up
+            while socket.read().is_ok() {}
+            (cookie, subscribe)
+        });
+
+        let received = RefCell::new(Vec::new());
+        let result = run_session(
+            &endpoint,
+            COOKIE,
+            &|| received.borrow().len() == 2,
+            &mut |notification| received.borrow_mut().push(notification),
+        );
+        let (cookie, subscribe) = server.join().unwrap();
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(cookie.as_deref(), Some(COOKIE));
+        assert_eq!(subscribe["id"], "1");
+        assert_eq!(subscribe["type"], "subscribe");
+        assert_eq!(subscribe["payload"]["query"], SUBSCRIPTION_QUERY);
+        assert_eq!(
+            received.into_inner(),
+            vec![
+                RoomNotification {
+                    space_id: "s1".into(),
+                    room_id: "mentioned-room".into(),
+                },
+                // No spaceId means a direct message
+                RoomNotification {
+                    space_id: "DM".into(),
+                    room_id: "r2".into(),
+                },
+            ]
+        );
+        assert!(crate::quiet_hours::mentioned("mentioned-room"));
+    }
+
+    #[test]
+    fn session_fails_on_subscription_error() {
+        let (listener, endpoint) = mock_server();
+        let server = thread::spawn(move || {
+            let (mut socket, _) = accept_ws(&listener);
+            read_json(&mut socket);
+            send_json(&mut socket, json!({ "type": "connection_ack" }));
+            read_json(&mut socket);
+            send_json(
+                &mut socket,
+                json!({ "id": "1", "type": "error", "payload": [{ "message": "unauthorized" }] }),
+            );
+            while socket.read().is_ok() {}
+        });
+
+        let result = run_session(&endpoint, COOKIE, &|| false, &mut |_| {});
+        server.join().unwrap();
+        assert!(result.unwrap_err().contains("unauthorized"));
+    }
+
+    #[test]
+    fn fetch_latest_message_returns_message() {
+        let (listener, endpoint) = mock_server();
+        let server = serve_http_once(
+            listener,
+            json!({ "data": { "roomEvents": [{
+                "actor": { "displayName": "Alice" },
+                "event": { "__typename": "MessagePostedEvent", "body": "Hello" },
+            }] } }),
+        );
+
+        let message = fetch_latest_message(&endpoint, COOKIE, "s1", "r1");
+        let (head, request) = server.join().unwrap();
+
+        assert_eq!(
+            message,
+            Ok(Some(LatestMessage {
+                author: Some("Alice".into()),
+                body: "Hello".into(),
+            }))
+        );
+        assert!(head.starts_with("POST /api/graphql "));
+        assert!(head
+            .lines()
+            .any(|line| line.eq_ignore_ascii_case(&format!("cookie: {COOKIE}"))));
+        assert_eq!(request["query"], ROOM_EVENTS_QUERY);
+        assert_eq!(request["variables"], json!({ "s": "s1", "r": "r1" }));
+    }
+
+    #[test]
+    fn fetch_mutations_lists_names_and_arguments() {
+        let (listener, endpoint) = mock_server();
+        let server = serve_http_once(
+            listener,
+            json!({ "data": { "__type": { "fields": [
+                { "name": "postMessage", "args": [{ "name": "roomId" }, { "name": "body" }] },
+                { "name": "logout", "args": [] },
+            ] } } }),
+        );
+
+        let mutations = fetch_mutations(&endpoint, COOKIE);
+        let (_, request) = server.join().unwrap();
+
+        assert_eq!(request["query"], MUTATIONS_QUERY);
+        assert_eq!(
+            mutations,
+            Ok(vec![
+                ("postMessage".into(), vec!["roomId".into(), "body".into()]),
+                ("logout".into(), vec![]),
+            ])
+        );
+    }
+
+    #[test]
+    fn fetch_mutations_without_introspection() {
+        let (listener, endpoint) = mock_server();
+        let server = serve_http_once(
+            listener,
+            json!({ "errors": [{ "message": "introspection is disabled" }] }),
+        );
+
+        let mutations = fetch_mutations(&endpoint, COOKIE);
+        server.join().unwrap();
+        assert_eq!(mutations, Ok(vec![]));
+    }
+
+    #[test]
+    fn fetch_latest_message_skips_other_events() {
+        let (listener, endpoint) = mock_server();
+        let server = serve_http_once(
+            listener,
+            json!({ "data": { "roomEvents": [{
+                "actor": { "displayName": "Bob" },
+                "event": { "__typename": "UserJoinedRoomEvent" },
+            }] } }),
+        );
+
+        let message = fetch_latest_message(&endpoint, COOKIE, "DM", "r1");
+        server.join().unwrap();
+        assert_eq!(message, Ok(None));
+    }
+}
@@ -0,0 +1,147 @@
+// Whether the tray icon is actually visible somewhere.
+//
+// On Linux the tray is a StatusNotifierItem, and building it succeeds even
+// when nothing on the session bus hosts one (stock GNOME, many Wayland
+// sessions). Hiding the window there would strand it, so the
+// StatusNotifierWatcher is asked for a registered host whenever it or a host
+// comes or goes on the bus, and close-to-tray falls back to minimizing until
+// one shows up. macOS and Windows always have a tray.
+
+#[cfg(all(target_os = "linux", feature = "tray"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(all(target_os = "linux", feature = "tray"))]
+use tauri::Manager;
+#[cfg(all(target_os = "linux", feature = "tray"))]
+use zbus::blocking::{Connection, Proxy};
+
+#[cfg(all(target_os = "linux", feature = "tray"))]
+const WATCHER: &str = "org.kde.StatusNotifierWatcher";
+
+#[cfg(all(target_os = "linux", feature = "tray"))]
+static AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// True when closing the main window to the tray leaves a way back to it.
+#[cfg(all(target_os = "linux", feature = "tray"))]
+pub fn available() -> bool {
+    AVAILABLE.load(Ordering::SeqCst)
+}
+
+// macOS and Windows always have a tray; builds without the tray feature
+// never do.
+#[cfg(not(all(target_os = "linux", feature = "tray")))]
+pub fn available() -> bool {
+    cfg!(feature = "tray")
+}
+
+/// Ask the StatusNotifierWatcher whether a host (a panel or shell extension
+/// that draws tray items) is registered. No watcher on the bus means no host.
+#[cfg(all(target_os = "linux", feature = "tray"))]
+fn host_registered(connection: &Connection) -> zbus::Result<bool> {
+    let reply = connection.call_method(
+        Some(WATCHER),
+        "/StatusNotifierWatcher",
+        Some("org.freedesktop.DBus.Properties"),
+        "Get",
+        &(WATCHER, "IsStatusNotifierHostRegistered"),
+    )?;
+    let value: zbus::zvariant::OwnedValue = reply.body().deserialize()?;
+    Ok(bool::try_from(value)?)
+}
+
+/// Check for a host again and react when that changed.
+#[cfg(all(target_os = "linux", feature = "tray"))]
+fn refresh(app: &tauri::AppHandle, connection: &Connection) {
+    let now = host_registered(connection).unwrap_or(false);
+    if AVAILABLE.swap(now, Ordering::SeqCst) == now {
+        return;
+    }
+    eprintln!(
+        "system tray host {}",
+        if now { "appeared" } else { "went away" }
+    );
+
+    // The window was hidden to a tray that no longer exists: bring it back
+    // rather than leave it unreachable.
+    if !now {
+        if let Some(window) = app.get_webview_window("main") {
+            if !window.is_visible().unwrap_or(true) {
+                let _ = window.show();
+                let _ = window.minimize();
+            }
+        }
+    }
+}
+
+/// Call refresh for every `signal` from `proxy` (filtered by `args`), on a
+/// thread of its own.
+#[cfg(all(target_os = "linux", feature = "tray"))]
+fn refresh_on(
+    app: &tauri::AppHandle,
+    proxy: zbus::Result<Proxy<'static>>,
+    signal: &'static str,
+    args: &'static [(u8, &'static str)],
+) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let subscribed = proxy.and_then(|proxy| {
+            let signals = proxy.receive_signal_with_args(signal, args)?;
+            Ok((proxy, signals))
+        });
+        let (proxy, signals) = match subscribed {
+            Ok(subscribed) => subscribed,
+            Err(e) => {
+                eprintln!("failed to subscribe to {signal}: {e}");
+                return;
+            }
+        };
+        for _ in signals {
+            refresh(&app, proxy.connection());
+        }
+    });
+}
+
+/// Check for a tray host now and keep watching for one appearing or going
+/// away for the lifetime of the app.
+#[cfg(all(target_os = "linux", feature = "tray"))]
+pub fn watch(app: tauri::AppHandle) {
+    let connection = match Connection::session() {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("failed to connect to the session bus: {e}");
+            return;
+        }
+    };
+    AVAILABLE.store(
+        host_registered(&connection).unwrap_or(false),
+        Ordering::SeqCst,
+    );
+
+    // The watcher itself starting or exiting (e.g. the panel restarting)
+    refresh_on(
+        &app,
+        Proxy::new(
+            &connection,
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+        ),
+        "NameOwnerChanged",
+        &[(0, WATCHER)],
+    );
+    // Hosts registering with, or leaving, a running watcher
+    for signal in [
+        "StatusNotifierHostRegistered",
+        "StatusNotifierHostUnregistered",
+    ] {
+        refresh_on(
+            &app,
+            Proxy::new(&connection, WATCHER, "/StatusNotifierWatcher", WATCHER),
+            signal,
+            &[],
+        );
+    }
+}
+
+#[cfg(all(not(target_os = "linux"), feature = "tray"))]
+pub fn watch(_app: tauri::AppHandle) {}
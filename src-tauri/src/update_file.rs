@@ -0,0 +1,244 @@
+// "Install Update from File…": installs a release downloaded by hand, for
+// machines that can't reach the update server.
+//
+// The file must come with its minisign signature (the .sig published next to
+// it in the release), checked against the updater `pubkey` in tauri.conf.json,
+// the same key the online updater trusts. The signed files are the updater
+// artifacts of a release:
+//
+//   Linux    Chatto_<version>_amd64.AppImage (AppImage installs only)
+//   macOS    Chatto.app.tar.gz
+//   Windows  Chatto_<version>_x64-setup.exe or Chatto_<version>_x64_en-US.msi
+//
+// Only the bytes that were verified are installed, never the file on disk,
+// so it can't be swapped between the check and the install.
+
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+use tauri_plugin_notification::NotificationExt;
+
+#[cfg(target_os = "linux")]
+const EXTENSIONS: &[&str] = &["AppImage"];
+#[cfg(target_os = "macos")]
+const EXTENSIONS: &[&str] = &["gz"];
+#[cfg(target_os = "windows")]
+const EXTENSIONS: &[&str] = &["exe", "msi"];
+
+/// Ask for an update file and its signature, then verify and install it.
+/// Blocks on the file dialogs, so call it off the main thread.
+pub fn pick_and_install(app: &tauri::AppHandle) {
+    if crate::policy::get().disable_update_check {
+        let _ = app
+            .notification()
+            .builder()
+            .title("Updates are managed by your administrator")
+            .body(&format!(
+                "{} can't install updates on this computer.",
+                crate::PRODUCT_NAME
+            ))
+            .show();
+        return;
+    }
+    if let Err(e) = run(app) {
+        let _ = app
+            .notification()
+            .builder()
+            .title("Update not installed")
+            .body(&e)
+            .show();
+    }
+}
+
+fn run(app: &tauri::AppHandle) -> Result<(), String> {
+    let Some(file) = app
+        .dialog()
+        .file()
+        .set_title("Choose the update file")
+        .add_filter(&format!("{} update", crate::PRODUCT_NAME), EXTENSIONS)
+        .blocking_pick_file()
+    else {
+        return Ok(());
+    };
+    let file = file.into_path().map_err(|e| e.to_string())?;
+
+    // Releases publish the signature as <file>.sig; ask only when it isn't
+    // next to the file.
+    let signature_path = match signature_beside(&file) {
+        Some(path) => path,
+        None => {
+            let Some(path) = app
+                .dialog()
+                .file()
+                .set_title("Choose the signature (.sig) file")
+                .add_filter("Signature", &["sig"])
+                .blocking_pick_file()
+            else {
+                return Ok(());
+            };
+            path.into_path().map_err(|e| e.to_string())?
+        }
+    };
+
+    let bytes =
+        std::fs::read(&file).map_err(|e| format!("Failed to read {}: {e}", file.display()))?;
+    let signature = std::fs::read_to_string(&signature_path)
+        .map_err(|e| format!("Failed to read {}: {e}", signature_path.display()))?;
+    verify(app, &bytes, &signature)?;
+
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let confirmed = app
+        .dialog()
+        .message(format!(
+            "{name} has a valid signature. Install it and restart {}?",
+            crate::PRODUCT_NAME
+        ))
+        .title("Install Update")
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Install and Restart".into(),
+            "Cancel".into(),
+        ))
+        .blocking_show();
+    if !confirmed {
+        return Ok(());
+    }
+    install(app, &file, &bytes)
+}
+
+fn signature_beside(file: &Path) -> Option<PathBuf> {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".sig");
+    Some(PathBuf::from(path)).filter(|path| path.is_file())
+}
+
+/// Text of a minisign key or signature. tauri.conf.json and the release .sig
+/// files hold it base64-encoded; a plain minisign file is accepted as well.
+fn decode(text: &str) -> String {
+    let text = text.trim();
+    base64::engine::general_purpose::STANDARD
+        .decode(text)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .unwrap_or_else(|| text.to_string())
+}
+
+fn verify(app: &tauri::AppHandle, data: &[u8], signature: &str) -> Result<(), String> {
+    let pubkey = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|key| key.as_str())
+        .ok_or("No updater public key is configured")?;
+    let key = PublicKey::decode(&decode(pubkey))
+        .map_err(|e| format!("Invalid updater public key: {e}"))?;
+    let signature = Signature::decode(&decode(signature))
+        .map_err(|e| format!("Invalid signature file: {e}"))?;
+    key.verify(data, &signature, true).map_err(|_| {
+        "The signature doesn't match this file or wasn't made with the release key".to_string()
+    })
+}
+
+/// Replace the running AppImage and restart. Writing beside it and renaming
+/// leaves the current version in place if anything fails.
+#[cfg(target_os = "linux")]
+fn install(app: &tauri::AppHandle, _file: &Path, bytes: &[u8]) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let appimage = std::env::var_os("APPIMAGE").map(PathBuf::from).ok_or(
+        "Only the AppImage can update itself; install the update with your package manager",
+    )?;
+    let staging = appimage.with_extension("update");
+    std::fs::write(&staging, bytes)
+        .map_err(|e| format!("Failed to write {}: {e}", staging.display()))?;
+    let installed = std::fs::set_permissions(&staging, std::fs::Permissions::from_mode(0o755))
+        .and_then(|()| std::fs::rename(&staging, &appimage));
+    if let Err(e) = installed {
+        let _ = std::fs::remove_file(&staging);
+        return Err(format!("Failed to replace {}: {e}", appimage.display()));
+    }
+    app.restart();
+}
+
+/// Unpack the new bundle beside the running one (so the swap is a rename on
+/// the same volume), swap them and restart.
+#[cfg(target_os = "macos")]
+fn install(app: &tauri::AppHandle, _file: &Path, bytes: &[u8]) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let bundle = exe
+        .ancestors()
+        .find(|path| path.extension().is_some_and(|ext| ext == "app"))
+        .ok_or_else(|| format!("{} isn't running from an app bundle", crate::PRODUCT_NAME))?
+        .to_path_buf();
+    let staging = bundle.with_extension("update");
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging)
+        .map_err(|e| format!("Failed to create {}: {e}", staging.display()))?;
+
+    let result = swap_bundle(&bundle, &staging, bytes);
+    let _ = std::fs::remove_dir_all(&staging);
+    result?;
+    app.restart();
+}
+
+#[cfg(target_os = "macos")]
+fn swap_bundle(bundle: &Path, staging: &Path, bytes: &[u8]) -> Result<(), String> {
+    let archive = staging.join("update.tar.gz");
+    std::fs::write(&archive, bytes)
+        .map_err(|e| format!("Failed to write {}: {e}", archive.display()))?;
+    let status = std::process::Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(staging)
+        .status()
+        .map_err(|e| format!("Failed to unpack the update: {e}"))?;
+    if !status.success() {
+        return Err("Failed to unpack the update".into());
+    }
+    let new_bundle = std::fs::read_dir(staging)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| path.extension().is_some_and(|ext| ext == "app"))
+        .ok_or("The update file doesn't contain an app")?;
+
+    let previous = staging.join("previous.app");
+    std::fs::rename(bundle, &previous)
+        .map_err(|e| format!("Failed to move {}: {e}", bundle.display()))?;
+    if let Err(e) = std::fs::rename(&new_bundle, bundle) {
+        let _ = std::fs::rename(&previous, bundle);
+        return Err(format!("Failed to install the update: {e}"));
+    }
+    Ok(())
+}
+
+/// Run the installer in passive mode and quit so it can replace Chatto; both
+/// installers start the new version when they're done.
+#[cfg(target_os = "windows")]
+fn install(app: &tauri::AppHandle, file: &Path, bytes: &[u8]) -> Result<(), String> {
+    let name = file.file_name().ok_or("Invalid update file")?;
+    let installer = std::env::temp_dir().join(name);
+    std::fs::write(&installer, bytes)
+        .map_err(|e| format!("Failed to write {}: {e}", installer.display()))?;
+
+    let msi = installer
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("msi"));
+    let spawned = if msi {
+        std::process::Command::new("msiexec")
+            .arg("/i")
+            .arg(&installer)
+            .args(["/passive", "AUTOLAUNCHAPP=True"])
+            .spawn()
+    } else {
+        std::process::Command::new(&installer)
+            .args(["/P", "/R"])
+            .spawn()
+    };
+    spawned.map_err(|e| format!("Failed to start the installer: {e}"))?;
+    app.exit(0);
+    Ok(())
+}
@@ -0,0 +1,414 @@
+// Self-update checks against the release manifests.
+//
+// The stable channel reads `latest.json` from the endpoints in
+// tauri.conf.json. The beta channel also reads the prerelease manifest,
+// `beta.json` next to each of them (CI attaches it to the latest stable
+// release when a prerelease is tagged), and takes whichever of the two is
+// newer, so beta testers still get stable releases that overtake the current
+// beta.
+//
+// Updates only ever move forward: switching from beta back to stable keeps
+// the installed prerelease until a stable release with a higher version is
+// out, instead of installing an older stable build.
+//
+// Custom endpoints (an internal mirror, from the policy file or settings)
+// replace the configured ones, with the beta manifest looked up next to them
+// the same way. Downloads are verified against the same pubkey either way.
+//
+// A manual check opens the update window with the release notes. The user
+// installs right away (download, install, restart) or stages the download,
+// which is installed when Chatto quits.
+//
+// Background checks run at startup and then every
+// `update_check_interval_hours`, unless turned off. They are skipped while the
+// release server is unreachable and announce each version only once.
+
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::settings::{self, UpdateChannel};
+
+// Prerelease manifest, published next to each stable manifest.
+const BETA_MANIFEST: &str = "beta.json";
+
+// How often the scheduler looks at whether a check is due against
+// `update_check_interval_hours`.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+// Update the window is showing.
+static PENDING: Mutex<Option<Update>> = Mutex::new(None);
+// Downloaded update waiting to be installed on quit.
+static STAGED: Mutex<Option<(Update, Vec<u8>)>> = Mutex::new(None);
+
+/// What the update window shows.
+#[derive(Clone, Debug, Serialize)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    /// Release notes from the manifest, usually Markdown.
+    pub notes: Option<String>,
+    /// Already downloaded and installed on quit.
+    pub staged: bool,
+}
+
+/// Payload of the "update-progress" event sent to the update window.
+#[derive(Clone, Debug, Serialize)]
+struct Progress {
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+/// Manifest URLs replacing the built-in ones: the policy's, else the user's.
+fn custom_endpoints(app: &tauri::AppHandle) -> Result<Option<Vec<tauri::Url>>, String> {
+    let urls = match &crate::policy::get().update_endpoints {
+        Some(urls) => urls.clone(),
+        None => settings::get(app).update_endpoints,
+    };
+    if urls.is_empty() {
+        return Ok(None);
+    }
+    urls.iter()
+        .map(|url| {
+            url.parse()
+                .map_err(|e| format!("Invalid update endpoint {url}: {e}"))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// Stable manifest URLs: the custom ones, else those from tauri.conf.json.
+fn endpoints(app: &tauri::AppHandle) -> Result<Vec<tauri::Url>, String> {
+    if let Some(endpoints) = custom_endpoints(app)? {
+        return Ok(endpoints);
+    }
+    app.config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("endpoints"))
+        .and_then(|endpoints| endpoints.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|url| url.as_str())
+        .map(|url| {
+            url.parse()
+                .map_err(|e| format!("Invalid update endpoint {url}: {e}"))
+        })
+        .collect()
+}
+
+/// The beta manifest next to a stable one: `.../latest.json` becomes
+/// `.../beta.json`.
+fn beta_endpoint(endpoint: &tauri::Url) -> tauri::Url {
+    let mut url = endpoint.clone();
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.pop().push(BETA_MANIFEST);
+    }
+    url
+}
+
+async fn check_endpoints(
+    app: &tauri::AppHandle,
+    endpoints: Vec<tauri::Url>,
+) -> Result<Option<Update>, String> {
+    app.updater_builder()
+        .endpoints(endpoints)
+        .map_err(|e| e.to_string())?
+        .build()
+        .map_err(|e| e.to_string())?
+        .check()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Newest update on `channel` that is newer than the running version.
+async fn find_update(
+    app: &tauri::AppHandle,
+    channel: UpdateChannel,
+) -> Result<Option<Update>, String> {
+    let endpoints = endpoints(app)?;
+
+    // The plugin's default comparator only accepts strictly newer versions,
+    // which is what keeps a channel switch from downgrading.
+    let beta_endpoints = endpoints.iter().map(beta_endpoint).collect();
+    let stable = check_endpoints(app, endpoints).await;
+    if channel == UpdateChannel::Stable {
+        return stable;
+    }
+
+    let beta = check_endpoints(app, beta_endpoints).await;
+
+    match (stable, beta) {
+        (Ok(Some(stable)), Ok(Some(beta))) => Ok(Some(if newer(&beta.version, &stable.version) {
+            beta
+        } else {
+            stable
+        })),
+        (Ok(Some(update)), _) | (_, Ok(Some(update))) => Ok(Some(update)),
+        (Ok(None), _) | (_, Ok(None)) => Ok(None),
+        (Err(e), Err(_)) => Err(e),
+    }
+}
+
+fn newer(a: &str, b: &str) -> bool {
+    match (semver::Version::parse(a), semver::Version::parse(b)) {
+        (Ok(a), Ok(b)) => a > b,
+        _ => false,
+    }
+}
+
+/// Check the configured channel for an update. `silent` checks (at startup)
+/// only announce an update; manual ones install it and report every outcome.
+pub async fn check(app: tauri::AppHandle, silent: bool) {
+    if crate::policy::get().disable_update_check {
+        if !silent {
+            let _ = app
+                .notification()
+                .builder()
+                .title("Updates are managed by your administrator")
+                .body(&format!(
+                    "{} does not check for updates on this computer.",
+                    crate::PRODUCT_NAME
+                ))
+                .show();
+        }
+        return;
+    }
+
+    let channel = settings::get(&app).update_channel;
+    match find_update(&app, channel).await {
+        Ok(Some(update)) => {
+            if silent {
+                if settings::get(&app).announced_update.as_ref() == Some(&update.version) {
+                    return;
+                }
+                let version = update.version.clone();
+                if let Err(e) = settings::update(&app, |s| s.announced_update = Some(version)) {
+                    eprintln!("failed to remember announced update: {e}");
+                }
+                let _ = app
+                    .notification()
+                    .builder()
+                    .title(&format!("{} update available", crate::PRODUCT_NAME))
+                    .body(&format!(
+                        "v{} is ready — use {} > Check for Updates to install",
+                        update.version,
+                        crate::PRODUCT_NAME
+                    ))
+                    .show();
+            } else {
+                *PENDING.lock().unwrap_or_else(|e| e.into_inner()) = Some(update);
+                if let Err(e) = open_window(&app) {
+                    eprintln!("failed to open update window: {e}");
+                }
+            }
+        }
+        Ok(None) => {
+            if !silent {
+                let _ = app
+                    .notification()
+                    .builder()
+                    .title(&format!("{} is up to date", crate::PRODUCT_NAME))
+                    .body(&format!(
+                        "v{} is the latest version.",
+                        app.package_info().version
+                    ))
+                    .show();
+            }
+        }
+        Err(e) => {
+            if !silent {
+                let _ = app
+                    .notification()
+                    .builder()
+                    .title("Update check failed")
+                    .body(&e)
+                    .show();
+            }
+        }
+    }
+}
+
+/// The running build is a prerelease, which a stable channel keeps until a
+/// newer stable release is out.
+pub fn running_prerelease(app: &tauri::AppHandle) -> bool {
+    !app.package_info().version.pre.is_empty()
+}
+
+fn open_window(app: &tauri::AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window("update") {
+        let _ = window.navigate(crate::frontend_url("/?update"));
+        let _ = window.show();
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+    tauri::WebviewWindowBuilder::new(
+        app,
+        "update",
+        tauri::WebviewUrl::External(crate::frontend_url("/?update")),
+    )
+    .title("Software Update")
+    .inner_size(480.0, 420.0)
+    .min_inner_size(360.0, 300.0)
+    .center()
+    .build()
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+/// The update the window offers, if any.
+pub fn info(app: &tauri::AppHandle) -> Option<UpdateInfo> {
+    let staged = STAGED
+        .lock()
+        .ok()?
+        .as_ref()
+        .map(|(update, _)| update.version.clone());
+    let update = PENDING.lock().ok()?.clone()?;
+    Some(UpdateInfo {
+        staged: staged.as_ref() == Some(&update.version),
+        version: update.version,
+        current_version: app.package_info().version.to_string(),
+        notes: update.body,
+    })
+}
+
+/// Download the offered update, reporting progress to the update window.
+/// With `now` it is installed and Chatto restarts; otherwise it is staged
+/// for [`install_staged`] on quit.
+pub async fn install(app: tauri::AppHandle, now: bool) -> Result<(), String> {
+    let staged = STAGED.lock().map_err(|e| e.to_string())?.take();
+    let (update, bytes) = match staged {
+        Some(staged) => staged,
+        None => {
+            let update = PENDING
+                .lock()
+                .map_err(|e| e.to_string())?
+                .clone()
+                .ok_or("No update to install")?;
+            let mut downloaded = 0u64;
+            let bytes = update
+                .download(
+                    |chunk, total| {
+                        downloaded += chunk as u64;
+                        let _ = app.emit_to(
+                            "update",
+                            "update-progress",
+                            Progress { downloaded, total },
+                        );
+                    },
+                    || {},
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+            (update, bytes)
+        }
+    };
+
+    if now {
+        if let Err(e) = update.install(&bytes) {
+            // Keep the download for a retry or for installing on quit
+            *STAGED.lock().map_err(|e| e.to_string())? = Some((update, bytes));
+            return Err(e.to_string());
+        }
+        app.restart();
+    }
+    *STAGED.lock().map_err(|e| e.to_string())? = Some((update, bytes));
+    Ok(())
+}
+
+/// Install an update staged with "Install on next quit". Runs as the app
+/// exits; on Windows the installer takes over the process.
+pub fn install_staged() {
+    let Some((update, bytes)) = STAGED.lock().ok().and_then(|mut staged| staged.take()) else {
+        return;
+    };
+    if let Err(e) = update.install(&bytes) {
+        eprintln!("failed to install staged update {}: {e}", update.version);
+    }
+}
+
+pub fn close_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("update") {
+        let _ = window.close();
+    }
+}
+
+/// Whether the update server can be reached at all, so an offline machine
+/// doesn't burn a check (and log a failure) every interval.
+fn online(app: &tauri::AppHandle) -> bool {
+    // Both channels' manifests live on the host of the first endpoint
+    let url = endpoints(app)
+        .ok()
+        .and_then(|endpoints| endpoints.into_iter().next());
+    url.and_then(|url| url.socket_addrs(|| None).ok())
+        .is_some_and(|addrs| {
+            addrs
+                .iter()
+                .any(|addr| TcpStream::connect_timeout(addr, Duration::from_secs(5)).is_ok())
+        })
+}
+
+/// Check for updates at startup and then on the configured interval for the
+/// lifetime of the app.
+pub fn schedule(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_check: Option<SystemTime> = None;
+        loop {
+            let settings = settings::get(&app);
+            // The setter and imports reject 0; this only guards a
+            // hand-edited config.json
+            let interval =
+                Duration::from_secs(u64::from(settings.update_check_interval_hours.max(1)) * 3600);
+            // Wall-clock time, so hours spent asleep count towards the interval
+            let due = last_check
+                .and_then(|at| at.elapsed().ok())
+                .is_none_or(|elapsed| elapsed >= interval);
+
+            // While offline, retry on the next poll instead of waiting out
+            // a whole interval
+            if settings.auto_update_check
+                && due
+                && !crate::policy::get().disable_update_check
+                && online(&app)
+            {
+                last_check = Some(SystemTime::now());
+                tauri::async_runtime::block_on(check(app.clone(), true));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beta(url: &str) -> String {
+        beta_endpoint(&url.parse().unwrap()).to_string()
+    }
+
+    #[test]
+    fn beta_manifest_sits_next_to_the_stable_one() {
+        assert_eq!(
+            beta("https://github.com/org/app/releases/latest/download/latest.json"),
+            "https://github.com/org/app/releases/latest/download/beta.json"
+        );
+        assert_eq!(
+            beta("https://updates.example.com/chatto/latest.json?arch=x86_64"),
+            "https://updates.example.com/chatto/beta.json?arch=x86_64"
+        );
+    }
+
+    #[test]
+    fn beta_manifest_for_a_directory_endpoint() {
+        assert_eq!(
+            beta("https://updates.example.com/chatto/"),
+            "https://updates.example.com/chatto/beta.json"
+        );
+    }
+}